@@ -0,0 +1,43 @@
+//! Compare the sequential and parallel updates, run with `cargo bench`.
+
+use cellular_automata::automata::{State, World};
+use cellular_automata::rule::Rule;
+use cellular_automata::{patterns, random};
+use std::time::{Duration, Instant};
+
+/// Random half-full world of `size` by `size` cells.
+fn soup_world(size: usize) -> World {
+    let mut world = World::new(size, size, 0, Rule::default());
+    let soup = patterns::soup(size, size, 0.5, &mut random::Rng::new(0));
+    world.stamp(&soup, (0, 0), State::ALIVE);
+    world.paused = false;
+    world
+}
+
+/// Mean duration of `update` over enough generations to last about a second.
+fn time_per_generation(size: usize, update: fn(&mut World)) -> Duration {
+    let mut world = soup_world(size);
+    let generations = (1 << 24) / (size * size) as u32 + 1;
+
+    let start = Instant::now();
+    for _ in 0..generations {
+        update(&mut world);
+    }
+    start.elapsed() / generations
+}
+
+fn main() {
+    for &size in &[32, 128, 512, 1000] {
+        let sequential = time_per_generation(size, World::update_sequential);
+        let parallel = time_per_generation(size, World::update_parallel);
+
+        println!(
+            "{:>4}x{:<4} sequential {:>10.1?}  parallel {:>10.1?}  speedup {:.2}",
+            size,
+            size,
+            sequential,
+            parallel,
+            sequential.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
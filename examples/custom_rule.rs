@@ -0,0 +1,55 @@
+//! Run an automaton defined outside of the crate, with
+//! `cargo run --example custom_rule`.
+
+use cellular_automata::automata::{Automaton, State, World};
+use cellular_automata::rule::{CellRule, Rule};
+use cellular_automata::{patterns, random};
+use std::sync::Arc;
+
+/// Majority vote: a cell takes the state of most of the 9 cells of its
+/// Moore neighbourhood, itself included, so a random soup settles into
+/// smooth blobs.
+#[derive(Debug)]
+struct Vote;
+
+impl CellRule for Vote {
+    fn next_state(&self, current: State, neighbour_states: &[State]) -> State {
+        let votes = neighbour_states
+            .iter()
+            .chain(Some(&current))
+            .filter(|&&state| state == State::ALIVE)
+            .count();
+        if votes * 2 > neighbour_states.len() + 1 {
+            State::ALIVE
+        } else {
+            State::DEAD
+        }
+    }
+}
+
+fn print(world: &World) {
+    for y in 0..world.height() {
+        let row: String = (0..world.width())
+            .map(|x| match world.cell_state(world.index(x, y)) {
+                Some(State::ALIVE) => '#',
+                _ => '.',
+            })
+            .collect();
+        println!("{}", row);
+    }
+    println!();
+}
+
+fn main() {
+    let mut world = World::new(48, 16, 0, Rule::default());
+    world.automaton = Automaton::Custom(Arc::new(Vote));
+    let soup = patterns::soup(48, 16, 0.5, &mut random::Rng::new(42));
+    world.stamp(&soup, (0, 0), State::ALIVE);
+    world.paused = false;
+
+    print(&world);
+    for _ in 0..8 {
+        world.update();
+    }
+    print(&world);
+}
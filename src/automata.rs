@@ -1,4 +1,21 @@
+use crate::formats::{apgcode, cells, rle, Pattern, PatternError};
+use crate::random::Rng;
+use crate::rule::{BlockRule, CellRule, Rule, RuleParseError, TableRule};
+use crate::theme::DrawOptions;
+use image::ImageResult;
 use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
 
 pub mod utils {
     pub fn coords_to_index(x: usize, y: usize, width: usize) -> usize {
@@ -10,10 +27,65 @@ pub mod utils {
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum State {
     ALIVE,
+    /// Dead cell, also the empty cell of Wireworld and the off cell of
+    /// Brian's Brain
     DEAD,
+    /// Wire of Wireworld, along which the electrons travel
+    CONDUCTOR,
+    /// Head of a Wireworld electron
+    HEAD,
+    /// Tail of a Wireworld electron
+    TAIL,
+    /// Cell of Brian's Brain that was on (alive) last generation, and is off
+    /// the next one
+    DYING,
+    /// Wall cell, never updated and counted as dead by its neighbours
+    IMMUTABLE,
+}
+
+impl State {
+    /// States of the cells of a `World` run by a `TableRule`, indexed by
+    /// their state in the table: dead, alive, then the dying state of Brian's
+    /// Brain and the conductors, heads and tails of Wireworld.
+    pub const TABLE_STATES: [State; 6] = [
+        State::DEAD,
+        State::ALIVE,
+        State::DYING,
+        State::CONDUCTOR,
+        State::HEAD,
+        State::TAIL,
+    ];
+
+    /// Index of the state in `TABLE_STATES`, walls reading as dead cells.
+    pub fn table_index(self) -> u8 {
+        match self {
+            State::DEAD | State::IMMUTABLE => 0,
+            State::ALIVE => 1,
+            State::DYING => 2,
+            State::CONDUCTOR => 3,
+            State::HEAD => 4,
+            State::TAIL => 5,
+        }
+    }
+
+    /// Whether the cell is in any state but `DEAD` or `IMMUTABLE`, the
+    /// states of Wireworld and dying cells counting as live.
+    pub fn is_live(self) -> bool {
+        !matches!(self, State::DEAD | State::IMMUTABLE)
+    }
+
+    fn from_alive(alive: bool) -> Self {
+        if alive {
+            State::ALIVE
+        } else {
+            State::DEAD
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -23,7 +95,7 @@ struct Position {
 }
 
 impl Position {
-    fn to_index(&self, width: usize) -> usize {
+    fn to_index(self, width: usize) -> usize {
         utils::coords_to_index(self.x, self.y, width)
     }
 
@@ -32,116 +104,3680 @@ impl Position {
         Self { x, y }
     }
 
-    fn left(&self, width: usize) -> Self {
-        let x = self.x.checked_sub(1).unwrap_or(width - 1);
-        Self { x, y: self.y }
+    /// Position `dx` columns to the right and `dy` rows down in a `width` by
+    /// `height` world, `None` past its `boundary`.
+    fn offset(
+        self,
+        dx: isize,
+        dy: isize,
+        width: usize,
+        height: usize,
+        boundary: Boundary,
+    ) -> Option<Self> {
+        Some(Self {
+            x: boundary.resolve(self.x as isize + dx, width)?,
+            y: boundary.resolve(self.y as isize + dy, height)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cell<T = ()> {
+    index: usize,
+    position: Position,
+    /// Number of generations the cell has been continuously alive for
+    age: u32,
+    /// Generations left before the trail of a dead cell fades away, set to
+    /// `MAX_TRAIL` when it dies and counted down every generation after
+    trail: u8,
+    payload: T,
+}
+
+impl<T> Cell<T> {
+    /// The cell going from the `current` state to the `next` one, aged if it
+    /// stays alive and leaving a trail if it dies.
+    fn advanced(self, current: State, next: State) -> Self {
+        let age = if next == State::ALIVE && current == State::ALIVE {
+            self.age.saturating_add(1)
+        } else {
+            0
+        };
+        let trail = if next.is_live() {
+            0
+        } else if current.is_live() {
+            MAX_TRAIL
+        } else {
+            self.trail.saturating_sub(1)
+        };
+
+        Self { age, trail, ..self }
     }
+}
+
+/// Longest period of the cycles detected by `World::settled_period`.
+const MAX_SETTLED_PERIOD: usize = 2;
+
+/// Longest trail left by the cells that die, in generations.
+pub const MAX_TRAIL: u8 = 64;
+
+/// Checkpoints kept by a new world for `World::undo`.
+pub const UNDO_LIMIT: usize = 100;
+
+/// Data attached to each cell of a `World<T>`, e.g. a species or an energy.
+/// Cells of a new world start with the default payload.
+pub trait Payload: Copy + Default + Send + Sync {}
+
+impl<T: Copy + Default + Send + Sync> Payload for T {}
+
+/// Next payload of a cell computed by `World::update`, from its current
+/// payload, its next state, and the current payloads of its neighbours.
+pub type PayloadRule<T> = fn(payload: T, state: State, neighbours: &[T]) -> T;
+
+/// Buffers reused from one cell to the next by `World::next_cell`, one per
+/// thread, so that rules reading the neighbours as a slice do not allocate
+/// it for every cell.
+#[derive(Default)]
+struct Scratch<T> {
+    states: Vec<State>,
+    payloads: Vec<T>,
+}
+
+/// Returned when rotating a world whose width and height differ.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NonSquareError {
+    pub width: usize,
+    pub height: usize,
+}
 
-    fn right(&self, width: usize) -> Self {
-        let x = self.x.checked_add(1).filter(|&v| v < width).unwrap_or(0);
-        Self { x, y: self.y }
+impl fmt::Display for NonSquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot rotate a non-square {}x{} world",
+            self.width, self.height
+        )
     }
+}
+
+impl error::Error for NonSquareError {}
+
+/// First bytes of the files written by `World::save_binary`.
+const BINARY_MAGIC: [u8; 4] = *b"CAWB";
+
+/// Version of the format written by `World::save_binary`.
+const BINARY_VERSION: u16 = 1;
+
+/// Largest number of cells of the worlds read by `World::load_binary`, that
+/// of a 4096 by 4096 world, so a corrupted header cannot make it allocate
+/// tens of gigabytes.
+const MAX_BINARY_CELLS: usize = 4096 * 4096;
+
+/// Error met while reading a world with `World::load_binary`.
+#[derive(Debug)]
+pub enum BinaryError {
+    Io(io::Error),
+    Invalid(String),
+}
 
-    fn top(&self, height: usize) -> Self {
-        let y = self.y.checked_sub(1).unwrap_or(height - 1);
-        Self { x: self.x, y }
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryError::Io(e) => write!(f, "{}", e),
+            BinaryError::Invalid(message) => write!(f, "invalid binary world: {}", message),
+        }
     }
+}
 
-    fn bottom(&self, height: usize) -> Self {
-        let y = self.y.checked_add(1).filter(|&v| v < height).unwrap_or(0);
-        Self { x: self.x, y }
+impl error::Error for BinaryError {}
+
+impl From<io::Error> for BinaryError {
+    fn from(e: io::Error) -> Self {
+        BinaryError::Io(e)
     }
 }
 
+/// Pixels darker than this are read as alive cells by `World::from_png`,
+/// provided they are at least this opaque.
+const PNG_THRESHOLD: u8 = 0x80;
+
+/// Worlds with fewer cells are updated sequentially by `Parallelism::Auto`,
+/// the overhead of spreading the work over threads outweighing the gain.
+pub const PARALLEL_THRESHOLD: usize = 64 * 64;
+
+/// How `World::update` spreads the computation of a generation over threads.
 #[derive(Clone, Copy, Debug, PartialEq)]
-struct Cell {
-    index: usize,
-    position: Position,
-    state: State,
-    neighbours_indexes: [usize; 8],
+pub enum Parallelism {
+    /// Parallel from `PARALLEL_THRESHOLD` cells, sequential below.
+    Auto,
+    Sequential,
+    Parallel,
+}
+
+impl FromStr for Parallelism {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Parallelism::Auto),
+            "sequential" => Ok(Parallelism::Sequential),
+            "parallel" => Ok(Parallelism::Parallel),
+            _ => Err(format!(
+                "unknown parallelism `{}`, expected one of auto, sequential, parallel",
+                s
+            )),
+        }
+    }
+}
+
+/// What lies beyond the edges of a world, gutter included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Boundary {
+    /// The world wraps around: cells past an edge are those of the opposite
+    /// edge
+    Toroidal,
+    /// Cells past the edges are dead walls
+    Fixed,
+    /// The edges are mirrors: the cells past an edge are those before it, in
+    /// reverse order, so a cell along an edge is its own neighbour
+    Reflective,
+}
+
+impl Boundary {
+    /// Coordinate of the cell at `coordinate` along an axis of `size` cells,
+    /// `None` for the walls of a fixed boundary.
+    fn resolve(self, coordinate: isize, size: usize) -> Option<usize> {
+        let size = size as isize;
+        match self {
+            Boundary::Toroidal => Some(coordinate.rem_euclid(size) as usize),
+            Boundary::Fixed => (0..size)
+                .contains(&coordinate)
+                .then_some(coordinate as usize),
+            Boundary::Reflective => {
+                let coordinate = coordinate.rem_euclid(2 * size);
+                Some(if coordinate < size {
+                    coordinate as usize
+                } else {
+                    (2 * size - 1 - coordinate) as usize
+                })
+            }
+        }
+    }
+
+    /// The next boundary, to cycle through them.
+    pub fn cycled(self) -> Self {
+        match self {
+            Boundary::Toroidal => Boundary::Fixed,
+            Boundary::Fixed => Boundary::Reflective,
+            Boundary::Reflective => Boundary::Toroidal,
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Boundary::Toroidal => write!(f, "toroidal"),
+            Boundary::Fixed => write!(f, "fixed"),
+            Boundary::Reflective => write!(f, "reflective"),
+        }
+    }
+}
+
+impl FromStr for Boundary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "toroidal" => Ok(Boundary::Toroidal),
+            "fixed" => Ok(Boundary::Fixed),
+            "reflective" => Ok(Boundary::Reflective),
+            _ => Err(format!(
+                "unknown boundary `{}`, expected one of toroidal, fixed, reflective",
+                s
+            )),
+        }
+    }
+}
+
+/// Cells counted as the neighbours of a cell by Life-like and table rules,
+/// within a radius (1 for the immediate neighbours).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Neighbourhood {
+    /// The cells of the surrounding square, 8 of them for radius 1
+    Moore,
+    /// The cells within a Manhattan distance of the radius, the 4
+    /// orthogonally adjacent cells for radius 1
+    VonNeumann,
+}
+
+impl Neighbourhood {
+    /// Number of neighbours of a cell, `(2r + 1)^2 - 1` for a Moore
+    /// neighbourhood of radius `r`, `2r(r + 1)` for a von Neumann one.
+    pub fn size(self, radius: usize) -> usize {
+        match self {
+            Neighbourhood::Moore => (2 * radius + 1).pow(2) - 1,
+            Neighbourhood::VonNeumann => 2 * radius * (radius + 1),
+        }
+    }
+
+    /// Offsets of the neighbours within `radius`, in row-major order.
+    fn offsets(self, radius: usize) -> Vec<(isize, isize)> {
+        let radius = radius as isize;
+        (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| match self {
+                Neighbourhood::Moore => (dx, dy) != (0, 0),
+                Neighbourhood::VonNeumann => (1..=radius).contains(&(dx.abs() + dy.abs())),
+            })
+            .collect()
+    }
+
+    /// The other neighbourhood.
+    pub fn toggled(self) -> Self {
+        match self {
+            Neighbourhood::Moore => Neighbourhood::VonNeumann,
+            Neighbourhood::VonNeumann => Neighbourhood::Moore,
+        }
+    }
+}
+
+impl fmt::Display for Neighbourhood {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Neighbourhood::Moore => write!(f, "moore"),
+            Neighbourhood::VonNeumann => write!(f, "von-neumann"),
+        }
+    }
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "moore" => Ok(Neighbourhood::Moore),
+            "von-neumann" => Ok(Neighbourhood::VonNeumann),
+            _ => Err(format!(
+                "unknown neighbourhood `{}`, expected one of moore, von-neumann",
+                s
+            )),
+        }
+    }
+}
+
+/// How the next generation of a `World` is computed.
+#[derive(Clone, Debug)]
+pub enum Automaton {
+    /// Each cell follows the `rule` of the world (or of its region) from the
+    /// number of its alive neighbours
+    LifeLike,
+    /// The cells of each 2x2 block of the Margolus neighbourhood are replaced
+    /// together. The partition is only consistent on worlds of even width and
+    /// height, gutter included.
+    Margolus(BlockRule),
+    /// Each cell follows the transitions of a table from its state and the
+    /// tally of the states of its neighbours, the states of the table being
+    /// those of `State::TABLE_STATES`. Cells reaching a state past them keep
+    /// their state.
+    Table(TableRule),
+    /// Electrons travel along conductors, alive cells counting as
+    /// conductors so wires can be painted
+    Wireworld,
+    /// Off cells turn on (alive) next to exactly two on cells, on cells
+    /// start dying and dying cells turn off
+    BriansBrain,
+    /// Each cell follows a rule defined outside of the crate, from its state
+    /// and the states of its neighbours
+    Custom(Arc<dyn CellRule>),
+}
+
+impl PartialEq for Automaton {
+    /// Custom automata are equal when they share the same rule.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Automaton::LifeLike, Automaton::LifeLike) => true,
+            (Automaton::Margolus(a), Automaton::Margolus(b)) => a == b,
+            (Automaton::Table(a), Automaton::Table(b)) => a == b,
+            (Automaton::Wireworld, Automaton::Wireworld) => true,
+            (Automaton::BriansBrain, Automaton::BriansBrain) => true,
+            (Automaton::Custom(a), Automaton::Custom(b)) => {
+                Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const ()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Automaton {
+    /// Name of the automaton as accepted by `from_str`, `margolus`, `table`
+    /// and `custom` for the rules it does not name.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Automaton::LifeLike => write!(f, "life-like"),
+            Automaton::Margolus(rule) if *rule == BlockRule::critters() => write!(f, "critters"),
+            Automaton::Margolus(rule) if *rule == BlockRule::billiard_ball() => {
+                write!(f, "billiard-ball")
+            }
+            Automaton::Margolus(_) => write!(f, "margolus"),
+            Automaton::Table(_) => write!(f, "table"),
+            Automaton::Wireworld => write!(f, "wireworld"),
+            Automaton::BriansBrain => write!(f, "brians-brain"),
+            Automaton::Custom(_) => write!(f, "custom"),
+        }
+    }
+}
+
+impl FromStr for Automaton {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "life-like" => Ok(Automaton::LifeLike),
+            "critters" => Ok(Automaton::Margolus(BlockRule::critters())),
+            "billiard-ball" => Ok(Automaton::Margolus(BlockRule::billiard_ball())),
+            "wireworld" => Ok(Automaton::Wireworld),
+            "brians-brain" => Ok(Automaton::BriansBrain),
+            _ => Err(format!(
+                "unknown automaton `{}`, expected one of life-like, critters, billiard-ball, wireworld, brians-brain",
+                s
+            )),
+        }
+    }
+}
+
+/// Automata that can be cycled through at runtime, with their names. The
+/// rule of the Margolus automata, Wireworld and Brian's Brain is left to
+/// Conway's.
+pub fn builtin_automata() -> Vec<(&'static str, Automaton, Rule)> {
+    let life_like = |name, rule| (name, Automaton::LifeLike, Rule::parse(rule).unwrap());
+    vec![
+        life_like("Life", "B3/S23"),
+        life_like("HighLife", "B36/S23"),
+        life_like("Seeds", "B2/S"),
+        life_like("Day & Night", "B3678/S34678"),
+        (
+            "Critters",
+            Automaton::Margolus(BlockRule::critters()),
+            Rule::default(),
+        ),
+        (
+            "Billiard ball machine",
+            Automaton::Margolus(BlockRule::billiard_ball()),
+            Rule::default(),
+        ),
+        ("Wireworld", Automaton::Wireworld, Rule::default()),
+        ("Brian's Brain", Automaton::BriansBrain, Rule::default()),
+    ]
+}
+
+/// Rectangle of cells, in coordinates relative to the top-left corner of the
+/// visible area.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// Translating pattern, as found by `World::classify_spaceship`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spaceship {
+    pub period: usize,
+    /// Horizontal displacement per period, positive toward the right
+    pub dx: isize,
+    /// Vertical displacement per period, positive toward the bottom
+    pub dy: isize,
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl fmt::Display for Spaceship {
+    /// Speed and direction, e.g. `c/4 diagonal` for a glider.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let distance = self.dx.unsigned_abs().max(self.dy.unsigned_abs());
+        let divisor = gcd(distance, self.period);
+        let (distance, period) = (distance / divisor, self.period / divisor);
+        let direction = if self.dx == 0 || self.dy == 0 {
+            "orthogonal"
+        } else if self.dx.abs() == self.dy.abs() {
+            "diagonal"
+        } else {
+            "oblique"
+        };
+
+        match (distance, period) {
+            (1, 1) => write!(f, "c {}", direction),
+            (1, _) => write!(f, "c/{} {}", period, direction),
+            (_, 1) => write!(f, "{}c {}", distance, direction),
+            _ => write!(f, "{}c/{} {}", distance, period, direction),
+        }
+    }
 }
 
-pub struct World {
+/// States of the cells of a `World` at a given generation, and the cells
+/// frozen, see `World::snapshot`. Ages and trails are left out, to keep
+/// snapshots small.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    generation: u64,
+    states: Vec<State>,
+    /// Indexes of the frozen cells, in ascending order
+    frozen: Vec<usize>,
+}
+
+impl Snapshot {
+    /// Snapshot of a world of `cell_count` cells (gutter included) whose
+    /// cells are dead but for the `(index, state)` of `cells`, and whose
+    /// `frozen` cells are frozen.
+    pub fn from_cells(
+        generation: u64,
+        cell_count: usize,
+        cells: &[(usize, State)],
+        frozen: &[usize],
+    ) -> Self {
+        let mut states = vec![State::DEAD; cell_count];
+        for &(index, state) in cells {
+            if let Some(current) = states.get_mut(index) {
+                *current = state;
+            }
+        }
+        let mut frozen: Vec<usize> = frozen
+            .iter()
+            .copied()
+            .filter(|&index| index < cell_count)
+            .collect();
+        frozen.sort_unstable();
+        frozen.dedup();
+
+        Self {
+            generation,
+            states,
+            frozen,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Indexes and states of the cells that are not dead, in ascending order.
+    pub fn cells(&self) -> Vec<(usize, State)> {
+        self.states
+            .iter()
+            .enumerate()
+            .filter(|(_, &state)| state != State::DEAD)
+            .map(|(index, &state)| (index, state))
+            .collect()
+    }
+
+    /// Indexes of the frozen cells, in ascending order.
+    pub fn frozen(&self) -> &[usize] {
+        &self.frozen
+    }
+}
+
+/// Snapshots of a `World` taken by `World::checkpoint`, to `undo` back to,
+/// and those undone, to `redo`. Clones of a world start with an empty
+/// history, so copying a world does not copy its snapshots.
+#[derive(Debug)]
+struct UndoHistory {
+    limit: usize,
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            limit: UNDO_LIMIT,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl Clone for UndoHistory {
+    fn clone(&self) -> Self {
+        Self {
+            limit: self.limit,
+            ..Self::default()
+        }
+    }
+}
+
+/// Grid of cells, each carrying a `T` payload (nothing by default).
+#[derive(Clone)]
+pub struct World<T = ()> {
     pub paused: bool,
-    cells: Vec<Cell>,
+    pub parallelism: Parallelism,
+    pub automaton: Automaton,
+    pub rule: Rule,
+    /// Rules applied instead of `rule` to the cells of their region, the
+    /// first region containing a cell winning
+    pub region_rules: Vec<(Rect, Rule)>,
+    /// Carrying capacity: births that would take the population over it are
+    /// dropped, the lowest indexes first
+    pub max_population: Option<usize>,
+    /// Update of the payloads, left untouched when `None`
+    pub payload_rule: Option<PayloadRule<T>>,
+    generation: u64,
+    width: usize,
+    height: usize,
+    gutter: usize,
+    /// State of each cell, kept apart from the rest of the cells so the
+    /// neighbour counts of `update` scan a tight array
+    states: Vec<State>,
+    cells: Vec<Cell<T>>,
+    /// Neighbours counted by Life-like and table rules, Margolus automata
+    /// working on blocks
+    neighbourhood: Neighbourhood,
+    /// Distance up to which cells count as neighbours
+    radius: usize,
+    /// Edges of the world for Life-like and table rules, Margolus automata
+    /// always wrapping around
+    boundary: Boundary,
+    neighbours: NeighbourTable,
+    frozen: Vec<bool>,
+    /// Cells outside of the mask are never updated and stay dead
+    active_mask: Option<Vec<bool>>,
+    changed: Vec<usize>,
+    /// Allocations the next generation is computed in, holding no cells
+    /// between updates
+    back_states: Vec<State>,
+    back: Vec<Cell<T>>,
+    /// States of the cells over the last `MAX_SETTLED_PERIOD` generations,
+    /// the latest first
+    previous_states: VecDeque<Vec<State>>,
+    history: UndoHistory,
 }
 
-fn neighbours_indexes(i: usize, width: usize, height: usize) -> [usize; 8] {
+/// Indexes of the neighbours of the cell at `i`, at the given `offsets`,
+/// those past a fixed `boundary` being left out. Offsets wrapping around a
+/// world narrower or shorter than the neighbourhood can reach the same cell,
+/// or the cell itself, several times.
+fn neighbours_indexes(
+    i: usize,
+    width: usize,
+    height: usize,
+    offsets: &[(isize, isize)],
+    boundary: Boundary,
+) -> impl Iterator<Item = usize> + '_ {
     let pos = Position::from_index(i, width);
+    offsets.iter().filter_map(move |&(dx, dy)| {
+        pos.offset(dx, dy, width, height, boundary)
+            .map(|pos| pos.to_index(width))
+    })
+}
 
-    [
-        pos.top(height).left(width).to_index(width),
-        pos.top(height).to_index(width),
-        pos.top(height).right(width).to_index(width),
-        pos.left(width).to_index(width),
-        pos.right(width).to_index(width),
-        pos.bottom(height).left(width).to_index(width),
-        pos.bottom(height).to_index(width),
-        pos.bottom(height).right(width).to_index(width),
-    ]
+/// Indexes of the neighbours of every cell of a world, in the order of the
+/// cells. Cells along a fixed boundary have fewer neighbours than the others.
+///
+/// A wrapping world narrower or shorter than the neighbourhood (e.g. 1 cell
+/// wide) lists each of the cells wrapped onto once, and never the cell
+/// itself, so cells are not counted several times. Mirrors still reflect
+/// the cells along them as several neighbours.
+#[derive(Clone, Default)]
+struct NeighbourTable {
+    indexes: Vec<usize>,
+    /// Position in `indexes` of the first neighbour of each cell, followed
+    /// by the length of `indexes`
+    starts: Vec<usize>,
+}
+
+impl NeighbourTable {
+    fn new(
+        width: usize,
+        height: usize,
+        neighbourhood: Neighbourhood,
+        radius: usize,
+        boundary: Boundary,
+    ) -> Self {
+        let offsets = neighbourhood.offsets(radius);
+        let span = 2 * radius + 1;
+        let wraps_onto_itself = boundary == Boundary::Toroidal && (width < span || height < span);
+        let mut indexes = Vec::with_capacity(width * height * offsets.len());
+        let mut starts = Vec::with_capacity(width * height + 1);
+        for index in 0..width * height {
+            let start = indexes.len();
+            starts.push(start);
+            for neighbour in neighbours_indexes(index, width, height, &offsets, boundary) {
+                if !wraps_onto_itself
+                    || (neighbour != index && !indexes[start..].contains(&neighbour))
+                {
+                    indexes.push(neighbour);
+                }
+            }
+        }
+        starts.push(indexes.len());
+
+        Self { indexes, starts }
+    }
+
+    fn of(&self, index: usize) -> &[usize] {
+        &self.indexes[self.starts[index]..self.starts[index + 1]]
+    }
+}
+
+/// Shannon entropy, in bits, of a distribution given as counts summing to `total`.
+fn shannon_entropy(counts: &[usize], total: usize) -> f64 {
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum()
 }
 
 impl World {
-    pub fn new(width: usize, height: usize) -> Self {
+    /// Create a dead world whose visible area is `width` by `height` cells.
+    /// The visible area is surrounded by `gutter` dead rows and columns that
+    /// are simulated but not drawn, so patterns can spill past the visible
+    /// edges before wrapping around. Cells follow the Life-like `rule`.
+    pub fn new(width: usize, height: usize, gutter: usize, rule: Rule) -> Self {
+        Self::with_payloads(width, height, gutter, rule)
+    }
+
+    /// Load a world from an image, sized to its dimensions: dark pixels
+    /// become alive cells, light or transparent ones dead cells.
+    pub fn from_png(path: &Path) -> ImageResult<World> {
+        let image = image::open(path)?.into_luma_alpha();
+        let (width, height) = image.dimensions();
+        let mut world = World::new(width as usize, height as usize, 0, Rule::default());
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [luma, alpha] = pixel.0;
+            if luma < PNG_THRESHOLD && alpha >= PNG_THRESHOLD {
+                world.set_cell_state(world.index(x as usize, y as usize), State::ALIVE);
+            }
+        }
+
+        Ok(world)
+    }
+
+    /// Create a world sized to the pattern of an apgcode, as given by
+    /// Catagolue, e.g. `xq4_153` for a glider.
+    pub fn from_apgcode(code: &str) -> Result<World, PatternError> {
+        let pattern = apgcode::parse(code)?;
+        let mut world = World::new(
+            pattern.width,
+            pattern.height,
+            0,
+            pattern.rule.unwrap_or_default(),
+        );
+        world.stamp(&pattern.cells, (0, 0), State::ALIVE);
+        Ok(world)
+    }
+
+    /// Create a world whose visible area is `width` by `height` cells, with
+    /// `pattern` at its center and running its rule if it records one. A
+    /// pattern larger than the world is cut down to its center if `clip` is
+    /// set, and refused otherwise.
+    pub fn from_pattern(
+        pattern: &Pattern,
+        width: usize,
+        height: usize,
+        clip: bool,
+    ) -> Result<World, PatternError> {
+        if !clip && (pattern.width > width || pattern.height > height) {
+            return Err(PatternError::DimensionMismatch {
+                declared: (width, height),
+                actual: (pattern.width, pattern.height),
+            });
+        }
+
+        let mut world = World::new(width, height, 0, pattern.rule.unwrap_or_default());
+        let left = (width as isize - pattern.width as isize) / 2;
+        let top = (height as isize - pattern.height as isize) / 2;
+        for &(x, y) in &pattern.cells {
+            let (x, y) = (x as isize + left, y as isize + top);
+            if (0..width as isize).contains(&x) && (0..height as isize).contains(&y) {
+                world.set_cell_state(world.index(x as usize, y as usize), State::ALIVE);
+            }
+        }
+        Ok(world)
+    }
+
+    /// Create a world of `width` by `height` cells with the pattern of an RLE
+    /// file at its center, refusing patterns larger than the world.
+    pub fn from_rle(rle: &str, width: usize, height: usize) -> Result<World, PatternError> {
+        World::from_pattern(&rle::parse(rle)?, width, height, false)
+    }
+
+    /// Create a world of `width` by `height` cells with the pattern of a
+    /// plaintext `.cells` file at its center, refusing patterns larger than
+    /// the world. Rows shorter than the others are padded with dead cells.
+    pub fn from_plaintext(text: &str, width: usize, height: usize) -> Result<World, PatternError> {
+        World::from_pattern(&cells::parse(text)?, width, height, false)
+    }
+
+    /// Write the visible area of the world in a compact binary format, all
+    /// integers being little-endian whatever the machine:
+    ///
+    /// - the magic bytes `CAWB` and the version of the format, as a `u16`
+    /// - the width and height, as `u32`s
+    /// - the length of the rule in B/S notation, as a `u16`, and the rule
+    /// - one bit per cell in row-major order, the first cell of each byte
+    ///   being its lowest bit, set for alive cells
+    ///
+    /// Only dead and alive cells fit in a bit: worlds with cells in any other
+    /// state, such as Wireworld or Brian's Brain worlds, are refused. The
+    /// automaton is not saved, the world being read back as Life-like.
+    pub fn save_binary(&self, mut writer: impl Write) -> io::Result<()> {
+        if let Some(state) = self
+            .states
+            .iter()
+            .find(|&&state| !matches!(state, State::ALIVE | State::DEAD))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "cannot save a world with {:?} cells, only alive and dead cells",
+                    state
+                ),
+            ));
+        }
+        let (width, height) = (self.width(), self.height());
+        let rule = self.rule.to_string();
+
+        writer.write_all(&BINARY_MAGIC)?;
+        writer.write_all(&BINARY_VERSION.to_le_bytes())?;
+        writer.write_all(&(width as u32).to_le_bytes())?;
+        writer.write_all(&(height as u32).to_le_bytes())?;
+        writer.write_all(&(rule.len() as u16).to_le_bytes())?;
+        writer.write_all(rule.as_bytes())?;
+
+        let mut bits = vec![0u8; (width * height).div_ceil(8)];
+        for (x, y) in self.live_coords() {
+            let bit = y * width + x;
+            bits[bit / 8] |= 1 << (bit % 8);
+        }
+        writer.write_all(&bits)
+    }
+
+    /// Read a world written by `save_binary`, without a gutter.
+    pub fn load_binary(mut reader: impl Read) -> Result<World, BinaryError> {
+        fn read<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+            let mut bytes = [0; N];
+            reader.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+
+        if read::<4>(&mut reader)? != BINARY_MAGIC {
+            return Err(BinaryError::Invalid(
+                "missing `CAWB` magic bytes".to_string(),
+            ));
+        }
+        let version = u16::from_le_bytes(read(&mut reader)?);
+        if version != BINARY_VERSION {
+            return Err(BinaryError::Invalid(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let width = u32::from_le_bytes(read(&mut reader)?) as usize;
+        let height = u32::from_le_bytes(read(&mut reader)?) as usize;
+        let cells = width
+            .checked_mul(height)
+            .filter(|&cells| cells <= MAX_BINARY_CELLS)
+            .ok_or_else(|| {
+                BinaryError::Invalid(format!(
+                    "{}x{} world larger than {} cells",
+                    width, height, MAX_BINARY_CELLS
+                ))
+            })?;
+        let mut rule = vec![0; u16::from_le_bytes(read(&mut reader)?) as usize];
+        reader.read_exact(&mut rule)?;
+        let rule = std::str::from_utf8(&rule)
+            .map_err(|_| BinaryError::Invalid("rule is not UTF-8".to_string()))?;
+        let rule = Rule::parse(rule).map_err(|e| BinaryError::Invalid(e.to_string()))?;
+
+        let mut bits = vec![0; cells.div_ceil(8)];
+        reader.read_exact(&mut bits)?;
+
+        let mut world = World::new(width, height, 0, rule);
+        for (bit, state) in world.states.iter_mut().enumerate() {
+            if bits[bit / 8] & (1 << (bit % 8)) != 0 {
+                *state = State::ALIVE;
+            }
+        }
+        Ok(world)
+    }
+
+    /// Write the world to the file at `path` with `save_binary`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        self.save_binary(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read a world from a file written by `save`, without a gutter.
+    pub fn load(path: &Path) -> io::Result<World> {
+        World::load_binary(io::BufReader::new(fs::File::open(path)?)).map_err(|e| match e {
+            BinaryError::Io(e) => e,
+            BinaryError::Invalid(message) => io::Error::new(io::ErrorKind::InvalidData, message),
+        })
+    }
+}
+
+impl<T: Payload> World<T> {
+    /// Create a dead world like `World::new`, whose cells carry a `T`
+    /// payload starting at `T::default()`.
+    pub fn with_payloads(width: usize, height: usize, gutter: usize, rule: Rule) -> Self {
+        let width = width + 2 * gutter;
+        let height = height + 2 * gutter;
+
         Self {
             paused: true,
+            parallelism: Parallelism::Auto,
+            automaton: Automaton::LifeLike,
+            rule,
+            region_rules: Vec::new(),
+            max_population: None,
+            payload_rule: None,
+            generation: 0,
+            width,
+            height,
+            gutter,
+            states: vec![State::DEAD; width * height],
             cells: (0..(width * height))
                 .map(|index| Cell {
                     index,
                     position: Position::from_index(index, width),
-                    state: State::DEAD,
-                    neighbours_indexes: neighbours_indexes(index, width, height),
+                    age: 0,
+                    trail: 0,
+                    payload: T::default(),
                 })
                 .collect(),
+            neighbourhood: Neighbourhood::Moore,
+            radius: 1,
+            boundary: Boundary::Toroidal,
+            neighbours: NeighbourTable::new(
+                width,
+                height,
+                Neighbourhood::Moore,
+                1,
+                Boundary::Toroidal,
+            ),
+            frozen: vec![false; width * height],
+            active_mask: None,
+            changed: Vec::new(),
+            back_states: Vec::new(),
+            back: Vec::new(),
+            previous_states: VecDeque::new(),
+            history: UndoHistory::default(),
         }
     }
 
+    /// Width of the visible area.
+    pub fn width(&self) -> usize {
+        self.width - 2 * self.gutter
+    }
+
+    /// Height of the visible area.
+    pub fn height(&self) -> usize {
+        self.height - 2 * self.gutter
+    }
+
+    /// Number of hidden rows and columns around each edge of the visible area.
+    pub fn gutter(&self) -> usize {
+        self.gutter
+    }
+
+    /// Kill and unfreeze every cell, reset their payloads, pause, and restart
+    /// counting generations from zero, keeping the settings of the world.
+    pub fn clear(&mut self) {
+        *self = Self {
+            parallelism: self.parallelism,
+            automaton: self.automaton.clone(),
+            neighbourhood: self.neighbourhood,
+            radius: self.radius,
+            boundary: self.boundary,
+            neighbours: std::mem::take(&mut self.neighbours),
+            region_rules: std::mem::take(&mut self.region_rules),
+            max_population: self.max_population,
+            payload_rule: self.payload_rule,
+            active_mask: self.active_mask.take(),
+            history: std::mem::take(&mut self.history),
+            ..Self::with_payloads(self.width(), self.height(), self.gutter, self.rule)
+        };
+    }
+
     pub fn set_cell_state(&mut self, index: usize, state: State) {
-        if let Some(cell) = self.cells.get_mut(index) {
-            cell.state = state
+        if state.is_live() && !self.is_active(index) {
+            return;
+        }
+
+        if let (Some(current), Some(cell)) = (self.states.get_mut(index), self.cells.get_mut(index))
+        {
+            if *current != state {
+                *current = state;
+                cell.age = 0;
+                cell.trail = 0;
+            }
         };
     }
 
-    pub fn update(&mut self) {
-        if self.paused {
+    /// Pin the cell to its current state until it is unfrozen.
+    pub fn freeze(&mut self, index: usize) {
+        if let Some(frozen) = self.frozen.get_mut(index) {
+            *frozen = true
+        };
+    }
+
+    pub fn unfreeze(&mut self, index: usize) {
+        if let Some(frozen) = self.frozen.get_mut(index) {
+            *frozen = false
+        };
+    }
+
+    pub fn is_frozen(&self, index: usize) -> bool {
+        self.frozen.get(index).copied().unwrap_or(false)
+    }
+
+    /// Restrict the world to the cells set in `mask`, indexed like the cells
+    /// (gutter included), or lift the restriction with `None`. Cells outside
+    /// of the mask are killed, never updated, count as dead for their
+    /// neighbours, and are drawn with the background color. Masks of another
+    /// size are ignored.
+    pub fn set_active_mask(&mut self, mask: Option<Vec<bool>>) {
+        if mask
+            .as_ref()
+            .is_some_and(|mask| mask.len() != self.cells.len())
+        {
             return;
         }
 
-        // A cell cannot mutate other cells, only itself
-        // This allows us to run the update in parallel (using rayon crate here)
-        let new_state: Vec<Cell> = self
-            .cells
-            .par_iter()
-            .map(|&cell| {
-                let alive_neighbours = cell
-                    .neighbours_indexes
-                    .iter()
-                    .map(|&index| self.cells[index])
-                    .filter(|cell| cell.state == State::ALIVE)
-                    .count();
-
-                let state = match alive_neighbours {
-                    3 => State::ALIVE,
-                    2 => cell.state,
-                    _ => State::DEAD,
-                };
+        self.active_mask = mask;
+        self.kill_inactive();
+    }
+
+    /// Whether the cell at `index` is inside the active mask, if any.
+    pub fn is_active(&self, index: usize) -> bool {
+        self.active_mask
+            .as_ref()
+            .is_none_or(|mask| mask.get(index).copied().unwrap_or(false))
+    }
+
+    /// Mask of the disc inscribed in the visible area, for `set_active_mask`.
+    pub fn disc_mask(&self) -> Vec<bool> {
+        let (width, height) = (self.width() as f64, self.height() as f64);
+        let radius = width.min(height) / 2.0;
+        let gutter = self.gutter as f64;
 
-                Cell { state, ..cell }
+        self.cells
+            .iter()
+            .map(|cell| {
+                let dx = cell.position.x as f64 + 0.5 - gutter - width / 2.0;
+                let dy = cell.position.y as f64 + 0.5 - gutter - height / 2.0;
+                dx * dx + dy * dy <= radius * radius
             })
+            .collect()
+    }
+
+    fn kill_inactive(&mut self) {
+        if let Some(mask) = &self.active_mask {
+            for ((state, cell), _) in self
+                .states
+                .iter_mut()
+                .zip(self.cells.iter_mut())
+                .zip(mask)
+                .filter(|(_, &active)| !active)
+            {
+                *state = State::DEAD;
+                cell.age = 0;
+            }
+        }
+    }
+
+    /// Index of the cell at `(x, y)`, coordinates being relative to the
+    /// top-left corner of the visible area.
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        utils::coords_to_index(x + self.gutter, y + self.gutter, self.width)
+    }
+
+    /// Indexes of the cells at most `radius` columns and rows away from the
+    /// cell at `index`, clipped to the visible area.
+    pub fn square_around(&self, index: usize, radius: usize) -> Vec<usize> {
+        let (x, y) = utils::index_to_coords(index, self.width);
+        let (left, top) = (self.gutter, self.gutter);
+        let (right, bottom) = (self.gutter + self.width(), self.gutter + self.height());
+        let columns = x.saturating_sub(radius).max(left)..(x + radius + 1).min(right);
+        let rows = y.saturating_sub(radius).max(top)..(y + radius + 1).min(bottom);
+
+        rows.flat_map(|y| {
+            columns
+                .clone()
+                .map(move |x| utils::coords_to_index(x, y, self.width))
+        })
+        .collect()
+    }
+
+    /// Set the state of every cell of `pattern`, offset by `origin` within the
+    /// visible area. Cells falling outside of the world wrap around its edges.
+    pub fn stamp(&mut self, pattern: &[(usize, usize)], origin: (usize, usize), state: State) {
+        let (origin_x, origin_y) = origin;
+
+        for &(x, y) in pattern {
+            let x = (self.gutter + origin_x + x) % self.width;
+            let y = (self.gutter + origin_y + y) % self.height;
+            self.set_cell_state(utils::coords_to_index(x, y, self.width), state);
+        }
+    }
+
+    /// Give each cell the state (and age and payload) of the cell at
+    /// `source(x, y)`.
+    fn rearrange(&mut self, source: impl Fn(usize, usize) -> (usize, usize) + Sync) {
+        let sources: Vec<(State, u32, T)> = self
+            .states
+            .iter()
+            .zip(self.cells.iter())
+            .map(|(&state, cell)| (state, cell.age, cell.payload))
             .collect();
+        let width = self.width;
 
-        self.cells = new_state;
+        self.states
+            .par_iter_mut()
+            .zip(self.cells.par_iter_mut())
+            .for_each(|(state, cell)| {
+                let (x, y) = source(cell.position.x, cell.position.y);
+                let (source_state, age, payload) = sources[utils::coords_to_index(x, y, width)];
+                *state = source_state;
+                cell.age = age;
+                cell.payload = payload;
+            });
+        self.kill_inactive();
     }
 
-    /// Draw the `World` state to the frame buffer.
-    pub fn draw(&self, frame: &mut [u8]) {
-        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-            let rgba: [u8; 4] = match self.cells[i].state {
-                State::ALIVE => [0x1E, 0x1E, 0x1E, 0xFF],
-                State::DEAD => [0xF8, 0xF8, 0xF8, 0xF8],
-            };
+    /// Mirror the world around its vertical axis.
+    pub fn flip_horizontal(&mut self) {
+        let width = self.width;
+        self.rearrange(|x, y| (width - 1 - x, y));
+    }
 
-            pixel.copy_from_slice(&rgba);
+    /// Mirror the world around its horizontal axis.
+    pub fn flip_vertical(&mut self) {
+        let height = self.height;
+        self.rearrange(|x, y| (x, height - 1 - y));
+    }
+
+    /// Rotate a square world a quarter turn clockwise.
+    pub fn rotate90(&mut self) -> Result<(), NonSquareError> {
+        if self.width != self.height {
+            return Err(NonSquareError {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        let size = self.width;
+        self.rearrange(|x, y| (y, size - 1 - x));
+        Ok(())
+    }
+
+    /// Shrink the world to the bounding box of its alive cells surrounded by
+    /// `margin` dead cells on each side, keeping its gutter and settings.
+    /// Region rules are moved along with the cells. An empty world is left
+    /// untouched.
+    pub fn trim(&mut self, margin: usize) {
+        let live: Vec<(usize, usize)> = self
+            .live_cells()
+            .into_iter()
+            .map(|index| utils::index_to_coords(index, self.width))
+            .collect();
+        if live.is_empty() {
+            return;
+        }
+        let min_x = live.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let max_x = live.iter().map(|&(x, _)| x).max().unwrap_or(0);
+        let min_y = live.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        let max_y = live.iter().map(|&(_, y)| y).max().unwrap_or(0);
+
+        // Position in the current world of the top-left corner of the new one
+        let origin_x = min_x as isize - margin as isize - self.gutter as isize;
+        let origin_y = min_y as isize - margin as isize - self.gutter as isize;
+
+        *self = self.cropped(
+            max_x - min_x + 1 + 2 * margin,
+            max_y - min_y + 1 + 2 * margin,
+            origin_x,
+            origin_y,
+        );
+    }
+
+    /// Resize the visible area to `width` by `height` cells, keeping the
+    /// cells present in both sizes aligned on the top-left corner, the gutter
+    /// and the settings. The cells added are dead.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        if (width, height) != (self.width(), self.height()) {
+            *self = self.cropped(width, height, 0, 0);
+        }
+    }
+
+    /// World whose visible area is `width` by `height` cells, with the cells,
+    /// region rules and settings of this one, its top-left corner (gutter
+    /// included) being at `(origin_x, origin_y)` in this one. Cells beyond
+    /// this world are dead, and outside of its mask if it has one.
+    fn cropped(&self, width: usize, height: usize, origin_x: isize, origin_y: isize) -> Self {
+        let mut cropped = Self::with_payloads(width, height, self.gutter, self.rule);
+        let mut mask = self
+            .active_mask
+            .as_ref()
+            .map(|_| vec![false; cropped.cells.len()]);
+        for cell in cropped.cells.iter_mut() {
+            let x = cell.position.x as isize + origin_x;
+            let y = cell.position.y as isize + origin_y;
+            if !(0..self.width as isize).contains(&x) || !(0..self.height as isize).contains(&y) {
+                continue;
+            }
+
+            let index = utils::coords_to_index(x as usize, y as usize, self.width);
+            cropped.states[cell.index] = self.states[index];
+            cell.age = self.cells[index].age;
+            cell.payload = self.cells[index].payload;
+            cropped.frozen[cell.index] = self.frozen[index];
+            if let Some(mask) = &mut mask {
+                mask[cell.index] = self.is_active(index);
+            }
+        }
+        cropped.active_mask = mask;
+
+        cropped.paused = self.paused;
+        cropped.parallelism = self.parallelism;
+        cropped.automaton = self.automaton.clone();
+        cropped.set_neighbourhood(self.neighbourhood);
+        cropped.set_radius(self.radius);
+        cropped.set_boundary(self.boundary);
+        cropped.max_population = self.max_population;
+        cropped.payload_rule = self.payload_rule;
+        cropped.generation = self.generation;
+        cropped.region_rules = self
+            .region_rules
+            .iter()
+            .filter_map(|&(rect, rule)| {
+                let shift = |start: usize, length: usize, origin: isize| {
+                    let start = start as isize - origin;
+                    let end = start + length as isize;
+                    (end > 0).then(|| (start.max(0) as usize, (end - start.max(0)) as usize))
+                };
+                let (x, width) = shift(rect.x, rect.width, origin_x)?;
+                let (y, height) = shift(rect.y, rect.height, origin_y)?;
+                Some((
+                    Rect {
+                        x,
+                        y,
+                        width,
+                        height,
+                    },
+                    rule,
+                ))
+            })
+            .collect();
+
+        cropped
+    }
+
+    /// Stamp `pattern` at the center of the visible area.
+    pub fn stamp_centered(&mut self, pattern: &[(usize, usize)], state: State) {
+        let (pattern_width, pattern_height) = crate::patterns::size(pattern);
+        let origin = (
+            self.width().saturating_sub(pattern_width) / 2,
+            self.height().saturating_sub(pattern_height) / 2,
+        );
+        self.stamp(pattern, origin, state);
+    }
+
+    /// Bring each cell of the visible area to life with probability
+    /// `density` (clamped to `[0, 1]`), killing the others.
+    pub fn randomize(&mut self, density: f64, rng: &mut Rng) {
+        let density = density.clamp(0.0, 1.0);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let state = if rng.gen_bool(density) {
+                    State::ALIVE
+                } else {
+                    State::DEAD
+                };
+                self.set_cell_state(self.index(x, y), state);
+            }
+        }
+    }
+
+    /// Randomize the world from `seed`: two worlds of the same size (and
+    /// active mask) randomized with the same density and seed have exactly
+    /// the same cells, on every platform (see `random`).
+    pub fn randomize_seeded(&mut self, density: f64, seed: u64) {
+        self.randomize(density, &mut Rng::new(seed));
+    }
+
+    /// Bring to life the cells of `pattern` repeated in `tile_w` by `tile_h`
+    /// tiles covering the visible area from its top-left corner. Cells outside
+    /// of their tile, or outside of the visible area for the partial tiles of
+    /// the right and bottom edges, are clipped.
+    pub fn tile_pattern(&mut self, coords: &[(usize, usize)], tile_w: usize, tile_h: usize) {
+        if tile_w == 0 || tile_h == 0 {
+            return;
+        }
+
+        let (width, height) = (self.width(), self.height());
+        for tile_y in (0..height).step_by(tile_h) {
+            for tile_x in (0..width).step_by(tile_w) {
+                for &(x, y) in coords {
+                    let (cell_x, cell_y) = (tile_x + x, tile_y + y);
+                    if x < tile_w && y < tile_h && cell_x < width && cell_y < height {
+                        self.set_cell_state(self.index(cell_x, cell_y), State::ALIVE);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// Count the neighbours of `neighbourhood` from now on.
+    pub fn set_neighbourhood(&mut self, neighbourhood: Neighbourhood) {
+        if neighbourhood != self.neighbourhood {
+            self.neighbourhood = neighbourhood;
+            self.rebuild_neighbours();
+        }
+    }
+
+    pub fn radius(&self) -> usize {
+        self.radius
+    }
+
+    /// Count the neighbours up to `radius` cells away from now on (at least
+    /// 1), as Larger than Life rules do, their births and survivals being
+    /// given by counts past 8 such as `B34-45/S34-58`.
+    pub fn set_radius(&mut self, radius: usize) {
+        let radius = radius.max(1);
+        if radius != self.radius {
+            self.radius = radius;
+            self.rebuild_neighbours();
+        }
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    /// Treat the edges of the world as `boundary` from now on.
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        if boundary != self.boundary {
+            self.boundary = boundary;
+            self.rebuild_neighbours();
+        }
+    }
+
+    fn rebuild_neighbours(&mut self) {
+        self.neighbours = NeighbourTable::new(
+            self.width,
+            self.height,
+            self.neighbourhood,
+            self.radius,
+            self.boundary,
+        );
+    }
+
+    /// Indexes of the neighbours of the cell at `index`.
+    fn neighbours_of(&self, index: usize) -> &[usize] {
+        self.neighbours.of(index)
+    }
+
+    fn count_alive_neighbours(&self, cell: &Cell<T>) -> usize {
+        self.count_neighbours(cell, State::ALIVE)
+    }
+
+    /// Number of neighbours of `cell` in `state`.
+    fn count_neighbours(&self, cell: &Cell<T>, state: State) -> usize {
+        // Hot loop of every generation: a plain loop adding booleans, without
+        // copying the neighbouring cells nor branching on their state
+        let mut count = 0;
+        for &neighbour in self.neighbours_of(cell.index) {
+            count += (self.states[neighbour] == state) as usize;
+        }
+        count
+    }
+
+    /// Number of alive cells among the neighbours of the cell at `index`.
+    pub fn alive_neighbours(&self, index: usize) -> usize {
+        self.cells
+            .get(index)
+            .map_or(0, |cell| self.count_alive_neighbours(cell))
+    }
+
+    /// Rule applied to the cell at `position`.
+    fn rule_at(&self, position: Position) -> &Rule {
+        if self.region_rules.is_empty() {
+            return &self.rule;
+        }
+
+        let visible = (
+            position.x.checked_sub(self.gutter),
+            position.y.checked_sub(self.gutter),
+        );
+        let (x, y) = match visible {
+            (Some(x), Some(y)) => (x, y),
+            _ => return &self.rule,
+        };
+
+        self.region_rules
+            .iter()
+            .find(|(rect, _)| rect.contains(x, y))
+            .map_or(&self.rule, |(_, rule)| rule)
+    }
+
+    /// Whether the cell at `position` is alive once its Margolus block is
+    /// replaced by `rule`. Blocks start on even coordinates on even
+    /// generations, odd coordinates on odd ones.
+    fn next_alive_in_block(&self, rule: &BlockRule, position: Position) -> bool {
+        let offset = (self.generation % 2) as usize;
+        let column = (position.x + self.width - offset) % 2;
+        let row = (position.y + self.height - offset) % 2;
+        let left = (position.x + self.width - column) % self.width;
+        let top = (position.y + self.height - row) % self.height;
+
+        let mut block = 0;
+        for (bit, &(x, y)) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter().enumerate() {
+            let x = (left + x) % self.width;
+            let y = (top + y) % self.height;
+            if self.states[utils::coords_to_index(x, y, self.width)] == State::ALIVE {
+                block |= 1 << bit;
+            }
+        }
+
+        rule.table[block] & (1 << (row * 2 + column)) != 0
+    }
+
+    /// Next state of `cell` in Wireworld: heads become tails, tails become
+    /// conductors, and conductors (or alive cells) become heads next to one
+    /// or two heads.
+    fn next_wireworld_state(&self, cell: &Cell<T>) -> State {
+        match self.states[cell.index] {
+            State::DEAD | State::DYING => State::DEAD,
+            State::IMMUTABLE => State::IMMUTABLE,
+            State::HEAD => State::TAIL,
+            State::TAIL => State::CONDUCTOR,
+            State::ALIVE | State::CONDUCTOR => match self.count_neighbours(cell, State::HEAD) {
+                1 | 2 => State::HEAD,
+                _ => State::CONDUCTOR,
+            },
+        }
+    }
+
+    /// Next state of `cell` in Brian's Brain: alive cells start dying, and
+    /// any other cell is alive next to exactly two alive cells, dead
+    /// otherwise.
+    fn next_brians_brain_state(&self, cell: &Cell<T>) -> State {
+        match self.states[cell.index] {
+            State::ALIVE => State::DYING,
+            State::DEAD => State::from_alive(self.count_alive_neighbours(cell) == 2),
+            _ => State::DEAD,
+        }
+    }
+
+    /// Next state of `cell`, and the rest of the cell.
+    fn next_cell(&self, cell: Cell<T>, scratch: &mut Scratch<T>) -> (State, Cell<T>) {
+        let current = self.states[cell.index];
+        if self.frozen[cell.index] || !self.is_active(cell.index) || current == State::IMMUTABLE {
+            return (current, cell);
+        }
+
+        let state = match &self.automaton {
+            Automaton::LifeLike => {
+                let alive_neighbours = self.count_alive_neighbours(&cell);
+                State::from_alive(
+                    self.rule_at(cell.position)
+                        .next_alive(current == State::ALIVE, alive_neighbours),
+                )
+            }
+            Automaton::Margolus(rule) => {
+                State::from_alive(self.next_alive_in_block(rule, cell.position))
+            }
+            Automaton::Table(table) => {
+                let neighbours = self.neighbours_of(cell.index);
+                let mut tally = [0; State::TABLE_STATES.len()];
+                // Neighbours past fixed edges are dead
+                tally[0] = self.neighbourhood.size(self.radius) - neighbours.len();
+                for &neighbour in neighbours {
+                    tally[self.states[neighbour].table_index() as usize] += 1;
+                }
+                let next = table.next_state(current.table_index(), &tally);
+                State::TABLE_STATES
+                    .get(next as usize)
+                    .copied()
+                    .unwrap_or(current)
+            }
+            Automaton::Wireworld => self.next_wireworld_state(&cell),
+            Automaton::BriansBrain => self.next_brians_brain_state(&cell),
+            Automaton::Custom(rule) => {
+                let neighbour_states = &mut scratch.states;
+                neighbour_states.clear();
+                neighbour_states.extend(
+                    self.neighbours_of(cell.index)
+                        .iter()
+                        .map(|&index| self.states[index]),
+                );
+                rule.next_state(current, neighbour_states)
+            }
+        };
+        let cell = cell.advanced(current, state);
+        let payload = match self.payload_rule {
+            Some(payload_rule) => {
+                let neighbours = &mut scratch.payloads;
+                neighbours.clear();
+                neighbours.extend(
+                    self.neighbours_of(cell.index)
+                        .iter()
+                        .map(|&index| self.cells[index].payload),
+                );
+                payload_rule(cell.payload, state, neighbours)
+            }
+            None => cell.payload,
+        };
+
+        (state, Cell { payload, ..cell })
+    }
+
+    /// Compute the next generation, unless the world is paused.
+    pub fn update(&mut self) {
+        if !self.paused {
+            self.step();
+        }
+    }
+
+    /// Compute the next generation, even when the world is paused.
+    pub fn step(&mut self) {
+        let parallel = match self.parallelism {
+            Parallelism::Auto => self.cells.len() >= PARALLEL_THRESHOLD,
+            Parallelism::Sequential => false,
+            Parallelism::Parallel => true,
+        };
+
+        if parallel {
+            self.update_parallel();
+        } else {
+            self.update_sequential();
+        }
+    }
+
+    /// Step until `pred` holds, for at most `max` generations. Return the
+    /// generation at which `pred` became true, or `None` if it never did.
+    pub fn step_until(&mut self, max: usize, pred: impl Fn(&Self) -> bool) -> Option<usize> {
+        for _ in 0..max {
+            self.step();
+            if pred(self) {
+                return Some(self.generation as usize);
+            }
+        }
+
+        None
+    }
+
+    /// Undo the births of `new_states` (and `new_cells`) that take the
+    /// population over `max_population`, starting with the lowest indexes.
+    /// Surviving cells are left alone, so a population already over the cap
+    /// only shrinks through deaths.
+    fn limit_births(&self, new_states: &mut [State], new_cells: &mut [Cell<T>]) {
+        let max_population = match self.max_population {
+            Some(max_population) => max_population,
+            None => return,
+        };
+        let population = new_states
+            .iter()
+            .filter(|&&state| state == State::ALIVE)
+            .count();
+        let mut excess = population.saturating_sub(max_population);
+
+        for (index, new) in new_states.iter_mut().enumerate() {
+            if excess == 0 {
+                break;
+            }
+            if *new == State::ALIVE && self.states[index] == State::DEAD {
+                *new = self.states[index];
+                new_cells[index] = self.cells[index];
+                excess -= 1;
+            }
+        }
+    }
+
+    /// Keep the states of the cells before computing the next generation,
+    /// dropping those older than `MAX_SETTLED_PERIOD` generations.
+    fn remember_states(&mut self) {
+        let mut states = match self.previous_states.len() {
+            MAX_SETTLED_PERIOD => self.previous_states.pop_back().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        states.clear();
+        states.extend_from_slice(&self.states);
+        self.previous_states.push_front(states);
+    }
+
+    /// Period of the cycle the world has settled into, `Some(1)` for a still
+    /// life and `Some(2)` for a period 2 oscillator, found by comparing the
+    /// states of the cells with those of the last generations. `None` while
+    /// the world keeps changing, or before it has run for long enough.
+    pub fn settled_period(&self) -> Option<usize> {
+        (1..=self.previous_states.len())
+            .find(|&period| self.previous_states[period - 1] == self.states)
+    }
+
+    /// Whether the last generation left every cell in the same state.
+    pub fn is_stable(&self) -> bool {
+        self.settled_period() == Some(1)
+    }
+
+    /// Make `new_states` and `new_cells`, computed in the back buffers, the
+    /// current generation, the previous one becoming the back buffers.
+    fn swap_in(&mut self, new_states: Vec<State>, new_cells: Vec<Cell<T>>) {
+        self.checkpoint();
+        self.remember_states();
+        self.back_states = std::mem::replace(&mut self.states, new_states);
+        self.back = std::mem::replace(&mut self.cells, new_cells);
+        // Kept empty so that cloning the world does not copy them
+        self.back_states.clear();
+        self.back.clear();
+        self.generation += 1;
+    }
+
+    /// Compute the next generation on the current thread, even when paused.
+    pub fn update_sequential(&mut self) {
+        let mut new_states = std::mem::take(&mut self.back_states);
+        let mut new_cells = std::mem::take(&mut self.back);
+        let mut scratch = Scratch::default();
+        for &cell in &self.cells {
+            let (state, cell) = self.next_cell(cell, &mut scratch);
+            new_states.push(state);
+            new_cells.push(cell);
+        }
+        self.limit_births(&mut new_states, &mut new_cells);
+
+        self.changed.clear();
+        self.changed.extend(
+            new_states
+                .iter()
+                .zip(self.states.iter())
+                .enumerate()
+                .filter(|(_, (new, old))| new != old)
+                .map(|(index, _)| index),
+        );
+        self.swap_in(new_states, new_cells);
+    }
+
+    /// Compute the next generation over all the threads of the rayon pool,
+    /// even when paused.
+    pub fn update_parallel(&mut self) {
+        // A cell cannot mutate other cells, only itself
+        // This allows us to run the update in parallel (using rayon crate here)
+        let mut new_states = std::mem::take(&mut self.back_states);
+        let mut new_cells = std::mem::take(&mut self.back);
+        self.cells
+            .par_iter()
+            .map_init(Scratch::default, |scratch, &cell| {
+                self.next_cell(cell, scratch)
+            })
+            .unzip_into_vecs(&mut new_states, &mut new_cells);
+        self.limit_births(&mut new_states, &mut new_cells);
+
+        self.changed.clear();
+        self.changed.par_extend(
+            new_states
+                .par_iter()
+                .zip(self.states.par_iter())
+                .enumerate()
+                .filter(|(_, (new, old))| new != old)
+                .map(|(index, _)| index),
+        );
+        self.swap_in(new_states, new_cells);
+    }
+
+    /// Indexes and states of the cells whose state changed during the last
+    /// generation, to bring a copy of the world up to date with
+    /// `apply_changes`.
+    pub fn changes(&self) -> Vec<(usize, State)> {
+        self.changed
+            .iter()
+            .map(|&index| (index, self.states[index]))
+            .collect()
+    }
+
+    /// Compute the next generation from the `changes` of a copy of the world
+    /// that computed it, ageing the cells and leaving trails as `step`
+    /// would, without counting any neighbour. Payloads are left as they are.
+    pub fn apply_changes(&mut self, changes: &[(usize, State)]) {
+        self.checkpoint();
+        self.remember_states();
+        for &(index, state) in changes {
+            if let Some(current) = self.states.get_mut(index) {
+                *current = state;
+            }
+        }
+
+        let (frozen, mask) = (&self.frozen, self.active_mask.as_deref());
+        self.cells
+            .par_iter_mut()
+            .zip(self.states.par_iter())
+            .zip(self.previous_states[0].par_iter())
+            .for_each(|((cell, &state), &current)| {
+                let pinned = frozen[cell.index]
+                    || mask.is_some_and(|mask| !mask[cell.index])
+                    || current == State::IMMUTABLE;
+                if !pinned {
+                    *cell = cell.advanced(current, state);
+                }
+            });
+
+        self.changed.clear();
+        self.changed.extend(changes.iter().map(|&(index, _)| index));
+        self.generation += 1;
+    }
+
+    pub fn cell_state(&self, index: usize) -> Option<State> {
+        self.states.get(index).copied()
+    }
+
+    /// Number of generations the cell at `index` has been continuously alive
+    /// for, `0` for a dead or newborn cell.
+    pub fn age(&self, index: usize) -> Option<u32> {
+        self.cells.get(index).map(|cell| cell.age)
+    }
+
+    /// Payload of the cell at `index`.
+    pub fn payload(&self, index: usize) -> Option<T> {
+        self.cells.get(index).map(|cell| cell.payload)
+    }
+
+    pub fn set_payload(&mut self, index: usize, payload: T) {
+        if let Some(cell) = self.cells.get_mut(index) {
+            cell.payload = payload;
+        }
+    }
+
+    /// Number of cells, gutter included, indexes going from `0` to
+    /// `cell_count() - 1`.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Number of live cells (see `State::is_live`).
+    pub fn population(&self) -> usize {
+        self.states
+            .par_iter()
+            .filter(|state| state.is_live())
+            .count()
+    }
+
+    /// Whether no cell is live.
+    pub fn is_empty(&self) -> bool {
+        !self.states.par_iter().any(|state| state.is_live())
+    }
+
+    /// Indexes of the live cells, in ascending (row-major) order whatever
+    /// the scheduling of the parallel scan.
+    pub fn live_cells(&self) -> Vec<usize> {
+        self.states
+            .par_iter()
+            .enumerate()
+            .filter(|(_, state)| state.is_live())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Coordinates of the alive cells of the visible area, relative to its
+    /// top-left corner, in row-major order.
+    pub fn live_coords(&self) -> Vec<(usize, usize)> {
+        let (width, height) = (self.width(), self.height());
+        self.live_cells()
+            .into_iter()
+            .map(|index| utils::index_to_coords(index, self.width))
+            .filter_map(|(x, y)| Some((x.checked_sub(self.gutter)?, y.checked_sub(self.gutter)?)))
+            .filter(|&(x, y)| x < width && y < height)
+            .collect()
+    }
+
+    /// Alive cells of the visible area, relative to the top-left corner of
+    /// their bounding box, along with the rule of the world if it is
+    /// Life-like.
+    pub fn to_pattern(&self) -> Pattern {
+        let cells = self.live_coords();
+        let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+
+        Pattern {
+            rule: match self.automaton {
+                Automaton::LifeLike => Some(self.rule),
+                _ => None,
+            },
+            ..Pattern::new(
+                cells
+                    .into_iter()
+                    .map(|(x, y)| (x - min_x, y - min_y))
+                    .collect(),
+            )
+        }
+    }
+
+    /// Alive cells of the visible area in RLE format, cropped to their
+    /// bounding box, a dead world giving a `0` by `0` pattern.
+    pub fn to_rle(&self) -> String {
+        rle::write(&self.to_pattern())
+    }
+
+    /// Hash of the alive cells, identical for two worlds in the same state.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.live_cells().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Shannon entropy, in bits, of the distribution of the number of alive
+    /// neighbours of the cells: `0` for a uniform world, up to `log2(n + 1)`
+    /// for cells of `n` neighbours (`log2(9)` for the Moore neighbourhood).
+    pub fn entropy(&self) -> f64 {
+        let mut histogram = vec![0usize; self.neighbourhood.size(self.radius) + 1];
+        for cell in &self.cells {
+            histogram[self.count_alive_neighbours(cell)] += 1;
+        }
+        shannon_entropy(&histogram, self.cells.len())
+    }
+
+    /// Shannon entropy, in bits, of the distribution of the configurations of
+    /// the non-overlapping `block` by `block` squares tiling the world
+    /// (partial squares at the right and bottom edges being ignored). `block`
+    /// is clamped to `1..=8`.
+    pub fn block_entropy(&self, block: usize) -> f64 {
+        let block = block.clamp(1, 8);
+        let mut histogram = HashMap::new();
+        let mut total = 0;
+
+        for block_y in (0..self.height / block * block).step_by(block) {
+            for block_x in (0..self.width / block * block).step_by(block) {
+                let mut configuration = 0u64;
+                for y in block_y..block_y + block {
+                    for x in block_x..block_x + block {
+                        let index = utils::coords_to_index(x, y, self.width);
+                        configuration <<= 1;
+                        configuration |= (self.states[index] == State::ALIVE) as u64;
+                    }
+                }
+                *histogram.entry(configuration).or_insert(0) += 1;
+                total += 1;
+            }
+        }
+
+        let counts: Vec<usize> = histogram.values().copied().collect();
+        shannon_entropy(&counts, total)
+    }
+
+    /// Number of groups of alive cells connected through their neighbours,
+    /// across the edges of the world when they wrap around.
+    pub fn connected_components(&self) -> usize {
+        let mut visited = vec![false; self.cells.len()];
+        let mut components = 0;
+        let mut stack = Vec::new();
+        let offsets = Neighbourhood::Moore.offsets(1);
+
+        for start in self.live_cells() {
+            if visited[start] {
+                continue;
+            }
+
+            components += 1;
+            visited[start] = true;
+            stack.push(start);
+            while let Some(index) = stack.pop() {
+                let neighbours =
+                    neighbours_indexes(index, self.width, self.height, &offsets, self.boundary);
+                for neighbour in neighbours {
+                    if !visited[neighbour] && self.states[neighbour] == State::ALIVE {
+                        visited[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Lowest index of a live cell, the first one met in row-major order.
+    pub fn first_live_cell(&self) -> Option<usize> {
+        self.states
+            .par_iter()
+            .position_first(|state| state.is_live())
+    }
+
+    /// Top-left corner of the bounding box of the alive cells, and their
+    /// coordinates relative to it.
+    fn normalized_live_cells(&self) -> ((usize, usize), Vec<(usize, usize)>) {
+        let cells: Vec<(usize, usize)> = self
+            .live_cells()
+            .into_iter()
+            .map(|index| utils::index_to_coords(index, self.width))
+            .collect();
+        let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+
+        let shape = cells
+            .into_iter()
+            .map(|(x, y)| (x - min_x, y - min_y))
+            .collect();
+        ((min_x, min_y), shape)
+    }
+
+    /// Signed shortest distance from `from` to `to` around a loop of `size`.
+    fn wrapped_offset(from: usize, to: usize, size: usize) -> isize {
+        let offset = (to + size - from) % size;
+        if offset > size / 2 {
+            offset as isize - size as isize
+        } else {
+            offset as isize
+        }
+    }
+
+    /// Step the world until its alive cells come back to their current shape,
+    /// for at most `max_period` generations. Return the period and the
+    /// displacement of the pattern if it moved, `None` if it did not come
+    /// back, died, or came back in place (still life or oscillator).
+    ///
+    /// The pattern is located through its bounding box, so it should not
+    /// straddle the edges of the world.
+    pub fn classify_spaceship(&mut self, max_period: usize) -> Option<Spaceship> {
+        let ((x, y), shape) = self.normalized_live_cells();
+        if shape.is_empty() {
+            return None;
+        }
+
+        for period in 1..=max_period {
+            self.step();
+            let ((new_x, new_y), new_shape) = self.normalized_live_cells();
+            if new_shape.is_empty() {
+                return None;
+            }
+
+            if new_shape == shape {
+                let dx = Self::wrapped_offset(x, new_x, self.width);
+                let dy = Self::wrapped_offset(y, new_y, self.height);
+                if dx == 0 && dy == 0 {
+                    return None;
+                }
+
+                return Some(Spaceship { period, dx, dy });
+            }
+        }
+
+        None
+    }
+
+    /// Rule of the world in B/S notation, e.g. `B3/S23`.
+    pub fn rule_string(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Run the Life-like rule written in B/S notation, keeping whether the
+    /// cells count themselves among their neighbours.
+    pub fn set_rule_str(&mut self, rule: &str) -> Result<(), RuleParseError> {
+        self.rule = Rule {
+            include_center: self.rule.include_center,
+            ..Rule::parse(rule)?
+        };
+        self.automaton = Automaton::LifeLike;
+        Ok(())
+    }
+
+    /// Name of the automaton run by the world if it is a built-in one, its
+    /// rule in B/S notation for other Life-like rules.
+    pub fn automaton_name(&self) -> String {
+        let builtin = builtin_automata()
+            .into_iter()
+            .find(|(_, automaton, rule)| self.runs(automaton, rule));
+
+        match (builtin, &self.automaton) {
+            (Some((name, _, _)), _) => name.to_string(),
+            (None, Automaton::Table(_)) => "Table rule".to_string(),
+            (None, _) => self.rule.to_string(),
+        }
+    }
+
+    /// Whether the world runs `automaton`, with `rule` if it is Life-like.
+    fn runs(&self, automaton: &Automaton, rule: &Rule) -> bool {
+        self.automaton == *automaton && (*automaton != Automaton::LifeLike || self.rule == *rule)
+    }
+
+    /// Switch to the built-in automaton following the current one (or to the
+    /// first one), keeping the cells as they are.
+    pub fn cycle_automaton(&mut self) {
+        let mut automata = builtin_automata();
+        let next = automata
+            .iter()
+            .position(|(_, automaton, rule)| self.runs(automaton, rule))
+            .map_or(0, |current| (current + 1) % automata.len());
+        let (_, automaton, rule) = automata.swap_remove(next);
+        self.automaton = automaton;
+        self.rule = rule;
+    }
+
+    /// Number of generations computed since the world was created.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Record the state of the cells, to come back to it with `restore`.
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot_into(Snapshot::from_cells(0, 0, &[], &[]))
+    }
+
+    /// Record the state of the cells in `snapshot`, reusing its allocations.
+    fn snapshot_into(&self, mut snapshot: Snapshot) -> Snapshot {
+        snapshot.generation = self.generation;
+        snapshot.states.clear();
+        snapshot.states.extend_from_slice(&self.states);
+        snapshot.frozen.clear();
+        snapshot.frozen.extend(
+            self.frozen
+                .iter()
+                .enumerate()
+                .filter(|(_, &frozen)| frozen)
+                .map(|(index, _)| index),
+        );
+        snapshot
+    }
+
+    /// Bring the cells back to a state recorded by `snapshot`, freezing the
+    /// cells it recorded frozen and unfreezing the others. Ages and trails
+    /// start over, and payloads are left as they are. Snapshots of a world of
+    /// another size are ignored.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        if snapshot.states.len() != self.cells.len() {
+            return;
+        }
+
+        self.states.copy_from_slice(&snapshot.states);
+        for cell in self.cells.iter_mut() {
+            cell.age = 0;
+            cell.trail = 0;
+        }
+        for frozen in self.frozen.iter_mut() {
+            *frozen = false;
+        }
+        for &index in &snapshot.frozen {
+            self.frozen[index] = true;
+        }
+        self.generation = snapshot.generation;
+        self.changed.clear();
+        self.previous_states.clear();
+        self.kill_inactive();
+    }
+
+    /// Record the cells, so that `undo` can bring them back. Generations
+    /// are checkpointed before being computed, edits have to be by calling
+    /// this first. Only the last `undo_limit` checkpoints are kept, and the
+    /// edits undone can no longer be redone.
+    pub fn checkpoint(&mut self) {
+        if self.history.limit == 0 {
+            return;
+        }
+
+        let reused = if self.history.undo.len() >= self.history.limit {
+            self.history.undo.pop_front()
+        } else {
+            None
+        };
+        let snapshot =
+            self.snapshot_into(reused.unwrap_or_else(|| Snapshot::from_cells(0, 0, &[], &[])));
+        self.history.undo.push_back(snapshot);
+        self.history.redo.clear();
+    }
+
+    /// Bring the cells back to the last checkpoint, if any, as `restore`
+    /// does. Return whether there was one.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo.pop_back() {
+            Some(snapshot) => {
+                let current = self.snapshot();
+                self.restore(&snapshot);
+                self.history.redo.push(current);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Bring the cells forward to the last state undone, if any and no
+    /// checkpoint was made since. Return whether there was one.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo.pop() {
+            Some(snapshot) => {
+                let current = self.snapshot();
+                self.restore(&snapshot);
+                if self.history.undo.len() >= self.history.limit {
+                    self.history.undo.pop_front();
+                }
+                self.history.undo.push_back(current);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn undo_limit(&self) -> usize {
+        self.history.limit
+    }
+
+    /// Keep up to `limit` checkpoints from now on (`UNDO_LIMIT` by default),
+    /// dropping the oldest ones past it. A limit of zero turns checkpoints
+    /// off, e.g. for worlds that are only ever stepped.
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.history.limit = limit;
+        while self.history.undo.len() > limit {
+            self.history.undo.pop_front();
+        }
+        if limit == 0 {
+            self.history.redo.clear();
+        }
+    }
+
+    /// Indexes of the cells whose state changed during the last generation,
+    /// in ascending order.
+    pub fn changed_indices(&self) -> &[usize] {
+        &self.changed
+    }
+
+    /// Draw the visible area of the `World` to the frame buffer.
+    pub fn draw(&self, frame: &mut [u8], options: &DrawOptions) {
+        let theme = &options.theme;
+        let (visible_width, visible_height) = (self.width(), self.height());
+        // Dead cells whose trail is above this are still fading
+        let trail_length = options.trail.min(MAX_TRAIL);
+        let faded = MAX_TRAIL - trail_length;
+
+        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+            let (x, y) = utils::index_to_coords(i, visible_width);
+            let index = self.index(x, y);
+            let cell = &self.cells[index];
+            let rgba = match self.states[index] {
+                _ if !self.is_active(index) => theme.background,
+                State::ALIVE if options.age_colors => theme.aged(cell.age),
+                State::ALIVE => theme.alive,
+                State::DEAD if cell.trail > faded => theme.trail(cell.trail - faded, trail_length),
+                State::DEAD if options.transparent_dead => [0, 0, 0, 0],
+                State::DEAD => theme.dead,
+                State::CONDUCTOR => theme.conductor,
+                State::HEAD => theme.head,
+                State::TAIL => theme.tail,
+                State::DYING => theme.dying,
+                State::IMMUTABLE => theme.immutable,
+            };
+            let rgba = if self.frozen[index] {
+                theme.frozen(rgba)
+            } else {
+                rgba
+            };
+            let on_seam = x == 0 || y == 0 || x + 1 == visible_width || y + 1 == visible_height;
+            let rgba = if options.seams && on_seam {
+                theme.seam(rgba)
+            } else {
+                rgba
+            };
+            let rgba = if options.cursor == Some(index) {
+                theme.highlighted(rgba)
+            } else {
+                rgba
+            };
+
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+
+    /// Save the visible area as a PNG image drawn by `draw`, one pixel per
+    /// cell.
+    pub fn save_png(&self, path: &Path, options: &DrawOptions) -> ImageResult<()> {
+        let (width, height) = (self.width(), self.height());
+        let mut frame = vec![0; width * height * 4];
+        self.draw(&mut frame, options);
+
+        image::save_buffer_with_format(
+            path,
+            &frame,
+            width as u32,
+            height as u32,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+    }
+}
+
+/// Serialized form of a `World`: the rule and the states of the cells of its
+/// visible area, in row-major order. Other settings are left out, and the
+/// neighbours are recomputed when deserializing.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SerializedWorld {
+    width: usize,
+    height: usize,
+    rule: String,
+    /// Left out of the B/S notation of `rule`, false when missing
+    #[serde(default)]
+    include_center: bool,
+    cells: Vec<State>,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for World {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (width, height) = (self.width(), self.height());
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| self.states[self.index(x, y)])
+            .collect();
+
+        SerializedWorld {
+            width,
+            height,
+            rule: self.rule.to_string(),
+            include_center: self.rule.include_center,
+            cells,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for World {
+    /// Deserialize a world without a gutter.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedWorld::deserialize(deserializer)?;
+        let (width, height) = (serialized.width, serialized.height);
+        if serialized.cells.len() != width * height {
+            return Err(de::Error::custom(format!(
+                "expected {} cells for a {}x{} world, found {}",
+                width * height,
+                width,
+                height,
+                serialized.cells.len()
+            )));
+        }
+        let rule = Rule {
+            include_center: serialized.include_center,
+            ..Rule::parse(&serialized.rule).map_err(de::Error::custom)?
+        };
+
+        let mut world = World::new(width, height, 0, rule);
+        for (current, state) in world.states.iter_mut().zip(serialized.cells) {
+            *current = state;
+        }
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns;
+    use crate::rule::ConwayRule;
+    use crate::theme::Theme;
+
+    #[test]
+    fn blinker_step_reports_the_toggled_cells() {
+        let mut world = World::new(5, 5, 0, Rule::default());
+        world.stamp(&patterns::blinker(), (1, 2), State::ALIVE);
+        world.step();
+
+        let mut toggled = [
+            world.index(1, 2),
+            world.index(3, 2),
+            world.index(2, 1),
+            world.index(2, 3),
+        ];
+        toggled.sort_unstable();
+        assert_eq!(world.changed_indices(), &toggled[..]);
+    }
+
+    #[test]
+    fn glider_runs_into_the_gutter_before_wrapping_around() {
+        let gutter = 4;
+        let mut world = World::new(10, 10, gutter, Rule::default());
+        world.stamp(&patterns::glider(), (7, 7), State::ALIVE);
+
+        // The glider moves one cell diagonally every 4 generations, up to the
+        // far edge of the gutter
+        for _ in 0..4 * gutter {
+            world.step();
+            assert_eq!(world.population(), 5);
+            assert!(world.live_cells().into_iter().all(|index| {
+                let (x, y) = utils::index_to_coords(index, world.width);
+                x >= gutter + 7 && y >= gutter + 7
+            }));
+        }
+    }
+
+    #[test]
+    fn frozen_cell_survives_a_step_that_would_kill_it() {
+        let mut world = World::new(5, 5, 0, Rule::default());
+        let lonely = world.index(2, 2);
+        world.set_cell_state(lonely, State::ALIVE);
+        world.freeze(lonely);
+        world.step();
+        assert_eq!(world.cell_state(lonely), Some(State::ALIVE));
+
+        world.unfreeze(lonely);
+        world.step();
+        assert_eq!(world.cell_state(lonely), Some(State::DEAD));
+    }
+
+    #[test]
+    fn flipping_twice_restores_the_world() {
+        let mut world = World::new(7, 5, 1, Rule::default());
+        world.randomize_seeded(0.5, 7);
+        let cells = world.live_cells();
+
+        world.flip_horizontal();
+        world.flip_horizontal();
+        assert_eq!(world.live_cells(), cells);
+        world.flip_vertical();
+        world.flip_vertical();
+        assert_eq!(world.live_cells(), cells);
+    }
+
+    #[test]
+    fn flip_horizontal_mirrors_left_to_right() {
+        let mut world = World::new(6, 3, 0, Rule::default());
+        world.stamp(&[(0, 0), (0, 1), (1, 1), (0, 2)], (0, 0), State::ALIVE);
+        world.flip_horizontal();
+
+        assert_eq!(world.live_coords(), [(5, 0), (4, 1), (5, 1), (5, 2)]);
+    }
+
+    #[test]
+    fn four_rotations_restore_the_world() {
+        let mut world = World::new(3, 3, 0, Rule::default());
+        // Asymmetric: an L shape
+        world.stamp(&[(0, 0), (0, 1), (0, 2), (1, 2)], (0, 0), State::ALIVE);
+        let cells = world.live_coords();
+
+        world.rotate90().unwrap();
+        // Rotated a quarter turn clockwise, the L lies along the top row
+        assert_eq!(world.live_coords(), [(0, 0), (1, 0), (2, 0), (0, 1)]);
+        for _ in 0..3 {
+            world.rotate90().unwrap();
+        }
+        assert_eq!(world.live_coords(), cells);
+    }
+
+    #[test]
+    fn rotating_a_non_square_world_fails() {
+        let mut world = World::new(4, 3, 0, Rule::default());
+        assert_eq!(
+            world.rotate90(),
+            Err(NonSquareError {
+                width: 4,
+                height: 3
+            })
+        );
+    }
+
+    #[test]
+    fn neighbours_wrap_around_a_small_world() {
+        let world = World::new(4, 3, 0, Rule::default());
+        // Cells 0 to 3 on the first row, the corners being 0, 3, 8 and 11
+        let expected = [
+            [1, 3, 4, 5, 7, 8, 9, 11],
+            [0, 2, 4, 5, 6, 8, 9, 10],
+            [1, 3, 5, 6, 7, 9, 10, 11],
+            [0, 2, 4, 6, 7, 8, 10, 11],
+            [0, 1, 3, 5, 7, 8, 9, 11],
+            [0, 1, 2, 4, 6, 8, 9, 10],
+            [1, 2, 3, 5, 7, 9, 10, 11],
+            [0, 2, 3, 4, 6, 8, 10, 11],
+            [0, 1, 3, 4, 5, 7, 9, 11],
+            [0, 1, 2, 4, 5, 6, 8, 10],
+            [1, 2, 3, 5, 6, 7, 9, 11],
+            [0, 2, 3, 4, 6, 7, 8, 10],
+        ];
+
+        for (index, expected) in expected.iter().enumerate() {
+            let mut neighbours = world.neighbours_of(index).to_vec();
+            neighbours.sort_unstable();
+            assert_eq!(neighbours, expected, "neighbours of cell {}", index);
+        }
+    }
+
+    #[test]
+    fn glider_is_a_diagonal_c4_spaceship() {
+        let mut world = World::new(10, 10, 0, Rule::default());
+        world.stamp(&patterns::glider(), (2, 2), State::ALIVE);
+
+        assert_eq!(
+            world.classify_spaceship(8),
+            Some(Spaceship {
+                period: 4,
+                dx: 1,
+                dy: 1
+            })
+        );
+    }
+
+    #[test]
+    fn von_neumann_cells_count_orthogonal_neighbours_only() {
+        let mut world = World::new(5, 5, 0, Rule::default());
+        world.set_neighbourhood(Neighbourhood::VonNeumann);
+        world.stamp(
+            &[(0, 0), (2, 0), (1, 1), (0, 2), (2, 2)],
+            (1, 1),
+            State::ALIVE,
+        );
+
+        // The 4 alive corners of the center cell are not counted
+        assert_eq!(world.alive_neighbours(world.index(2, 2)), 0);
+        assert_eq!(world.alive_neighbours(world.index(2, 1)), 3);
+    }
+
+    #[test]
+    fn toggling_the_neighbourhood_changes_the_evolution() {
+        let mut moore = World::new(12, 12, 0, Rule::default());
+        moore.stamp(&patterns::glider(), (4, 4), State::ALIVE);
+        moore.step();
+        let mut von_neumann = moore.clone();
+        von_neumann.set_neighbourhood(Neighbourhood::VonNeumann);
+        assert_eq!(von_neumann.live_cells(), moore.live_cells());
+
+        moore.step();
+        von_neumann.step();
+        assert_ne!(von_neumann.live_cells(), moore.live_cells());
+
+        // Toggling back counts the diagonals again
+        let mut world = World::new(5, 5, 0, Rule::default());
+        world.set_cell_state(world.index(1, 1), State::ALIVE);
+        let center = world.index(2, 2);
+        assert_eq!(world.alive_neighbours(center), 1);
+        world.set_neighbourhood(Neighbourhood::VonNeumann);
+        assert_eq!(world.alive_neighbours(center), 0);
+        world.set_neighbourhood(Neighbourhood::Moore);
+        assert_eq!(world.alive_neighbours(center), 1);
+    }
+
+    #[test]
+    fn radius_two_counts_the_surrounding_square() {
+        let mut world = World::new(9, 9, 0, Rule::default());
+        world.set_radius(2);
+        let center = world.index(4, 4);
+        world.set_cell_state(center, State::ALIVE);
+
+        let counted: Vec<usize> = (0..world.cell_count())
+            .filter(|&i| world.alive_neighbours(i) == 1)
+            .collect();
+        let mut around = world.square_around(center, 2);
+        around.retain(|&i| i != center);
+        around.sort_unstable();
+        assert_eq!(counted, around);
+        assert_eq!(counted.len(), 24);
+    }
+
+    #[test]
+    fn larger_than_life_rules_count_past_eight() {
+        let (width, height) = (23, 19);
+        let rule = Rule::parse("B10-13/S9-16").unwrap();
+        let mut world = World::new(width, height, 0, rule);
+        world.set_radius(2);
+        world.stamp(
+            &patterns::soup(width, height, 0.45, &mut Rng::new(3)),
+            (0, 0),
+            State::ALIVE,
+        );
+        let mut reference: Vec<bool> = (0..width * height)
+            .map(|index| world.cell_state(index) == Some(State::ALIVE))
+            .collect();
+
+        for generation in 1..=10 {
+            reference = (0..width * height)
+                .map(|index| {
+                    let (x, y) = (index % width, index / width);
+                    let alive = (0..25)
+                        .map(|offset| (offset % 5, offset / 5))
+                        .filter(|&offset| offset != (2, 2))
+                        .filter(|&(dx, dy)| {
+                            let x = (x + width + dx - 2) % width;
+                            let y = (y + height + dy - 2) % height;
+                            reference[y * width + x]
+                        })
+                        .count();
+                    rule.next_alive(reference[index], alive)
+                })
+                .collect();
+            world.step();
+            let expected: Vec<usize> = (0..reference.len()).filter(|&i| reference[i]).collect();
+            assert_eq!(world.live_cells(), expected, "generation {}", generation);
+        }
+        assert!(!world.is_empty());
+    }
+
+    #[test]
+    fn offsets_wrap_around_any_distance() {
+        let corner = Position { x: 0, y: 1 };
+        assert_eq!(
+            corner.offset(-2, -3, 5, 4, Boundary::Toroidal),
+            Some(Position { x: 3, y: 2 })
+        );
+        assert_eq!(
+            corner.offset(7, 0, 5, 4, Boundary::Toroidal),
+            Some(Position { x: 2, y: 1 })
+        );
+        assert_eq!(corner.offset(-1, 0, 5, 4, Boundary::Fixed), None);
+    }
+
+    #[test]
+    fn fixed_edges_are_dead_walls() {
+        let mut world = World::new(4, 4, 0, Rule::default());
+        world.set_boundary(Boundary::Fixed);
+        world.stamp(&[(3, 3), (0, 3), (3, 0)], (0, 0), State::ALIVE);
+
+        // Wrapping around, the corner at (0, 0) would see the 3 others
+        assert_eq!(world.alive_neighbours(world.index(0, 0)), 0);
+        world.step();
+        assert_eq!(world.cell_state(world.index(0, 0)), Some(State::DEAD));
+    }
+
+    #[test]
+    fn reflective_corner_cell_sees_itself_three_times() {
+        let mut world = World::new(4, 4, 0, Rule::default());
+        world.set_boundary(Boundary::Reflective);
+        world.set_cell_state(world.index(0, 0), State::ALIVE);
+
+        // Mirrored across the left edge, the top edge and the corner
+        assert_eq!(world.alive_neighbours(world.index(0, 0)), 3);
+        assert_eq!(world.alive_neighbours(world.index(1, 0)), 2);
+        assert_eq!(world.alive_neighbours(world.index(1, 1)), 1);
+        assert_eq!(world.alive_neighbours(world.index(3, 3)), 0);
+    }
+
+    #[test]
+    fn glider_rle_is_loaded_centered() {
+        let world = World::from_rle(
+            "#N Glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n",
+            5,
+            5,
+        )
+        .unwrap();
+
+        assert_eq!(world.live_cells(), vec![7, 13, 16, 17, 18]);
+        assert_eq!(world.rule, Rule::default());
+    }
+
+    #[test]
+    fn patterns_larger_than_the_world_are_refused_or_clipped() {
+        let pattern = rle::parse("x = 5, y = 1\n5o!").unwrap();
+
+        assert!(matches!(
+            World::from_pattern(&pattern, 3, 3, false),
+            Err(PatternError::DimensionMismatch { .. })
+        ));
+        let clipped = World::from_pattern(&pattern, 3, 3, true).unwrap();
+        assert_eq!(clipped.live_cells(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn rle_round_trip_keeps_the_alive_cells() {
+        let mut world = World::new(12, 10, 0, Rule::default());
+        world.stamp(&patterns::glider(), (2, 1), State::ALIVE);
+        world.stamp(&[(0, 0), (1, 0), (2, 0)], (6, 7), State::ALIVE);
+
+        let rle = world.to_rle();
+        assert!(rle.starts_with("x = 7, y = 7"), "{}", rle);
+        let pattern = rle::parse(&rle).unwrap();
+        let mut copy = World::new(12, 10, 0, Rule::default());
+        copy.stamp(&pattern.cells, (2, 1), State::ALIVE);
+        assert_eq!(copy.live_cells(), world.live_cells());
+    }
+
+    #[test]
+    fn empty_world_is_written_as_an_empty_rle() {
+        let rle = World::new(4, 4, 0, Rule::default()).to_rle();
+
+        assert!(rle.starts_with("x = 0, y = 0"), "{}", rle);
+        assert!(rle.trim_end().ends_with('!'));
+        assert!(World::from_rle(&rle, 4, 4).unwrap().is_empty());
+    }
+
+    #[test]
+    fn plaintext_blinker_oscillates() {
+        let text = "!Name: Blinker\n!\n.O\n.O.\n.O\n";
+        let mut world = World::from_plaintext(text, 5, 5).unwrap();
+        let vertical = world.live_cells();
+        assert_eq!(vertical, vec![7, 12, 17]);
+
+        world.step();
+        assert_eq!(world.live_cells(), vec![11, 12, 13]);
+        world.step();
+        assert_eq!(world.live_cells(), vertical);
+    }
+
+    #[test]
+    fn randomized_population_follows_the_density() {
+        let mut world = World::new(100, 100, 2, Rule::default());
+        world.randomize(0.3, &mut Rng::new(42));
+        let population = world.population();
+        assert!((2_700..3_300).contains(&population), "{}", population);
+
+        world.randomize(1.5, &mut Rng::new(42));
+        assert_eq!(world.population(), 100 * 100);
+        world.randomize(-1.0, &mut Rng::new(42));
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn equal_seeds_randomize_identical_worlds() {
+        let seeded = |seed| {
+            let mut world = World::new(40, 30, 1, Rule::default());
+            world.randomize_seeded(0.5, seed);
+            world
+        };
+
+        assert_eq!(seeded(7).live_cells(), seeded(7).live_cells());
+        assert_ne!(seeded(7).live_cells(), seeded(8).live_cells());
+    }
+
+    #[test]
+    fn saved_worlds_load_back_the_same() {
+        let mut world = World::new(13, 7, 0, Rule::parse("B36/S23").unwrap());
+        world.stamp(&patterns::glider(), (11, 5), State::ALIVE);
+        let path = std::env::temp_dir().join(format!("saved-world-{}.cawb", std::process::id()));
+
+        world.save(&path).unwrap();
+        let loaded = World::load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!((loaded.width(), loaded.height()), (13, 7));
+        assert_eq!(loaded.rule, world.rule);
+        assert_eq!(loaded.live_cells(), world.live_cells());
+        for i in 0..world.cell_count() {
+            assert_eq!(loaded.alive_neighbours(i), world.alive_neighbours(i));
+        }
+    }
+
+    #[test]
+    fn png_has_one_pixel_per_visible_cell() {
+        let mut world = World::new(5, 3, 2, Rule::default());
+        world.stamp(&[(0, 0), (4, 2)], (0, 0), State::ALIVE);
+        let path = std::env::temp_dir().join(format!("frame-{}.png", std::process::id()));
+
+        world.save_png(&path, &DrawOptions::default()).unwrap();
+        let image = image::open(&path);
+        fs::remove_file(&path).unwrap();
+        let image = image.unwrap().to_rgba();
+        let theme = Theme::default();
+        assert_eq!(image.dimensions(), (5, 3));
+        assert_eq!(image.get_pixel(0, 0).0, theme.alive);
+        assert_eq!(image.get_pixel(4, 2).0, theme.alive);
+        assert_eq!(image.get_pixel(1, 0).0, theme.dead);
+        assert_eq!(image.get_pixel(2, 1).0, theme.dead);
+    }
+
+    #[test]
+    fn dark_pixels_of_a_png_are_loaded_alive() {
+        let mut pixels = vec![0xFF; 5 * 5 * 4];
+        for &(x, y) in &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2), (4, 4)] {
+            pixels[(y * 5 + x) * 4..(y * 5 + x) * 4 + 3].copy_from_slice(&[0x10, 0x20, 0x10]);
+        }
+        // Dark but transparent
+        pixels[(4 * 5 + 4) * 4 + 3] = 0;
+        let path = std::env::temp_dir().join(format!("glider-{}.png", std::process::id()));
+        image::save_buffer(&path, &pixels, 5, 5, image::ColorType::Rgba8).unwrap();
+
+        let world = World::from_png(&path);
+        fs::remove_file(&path).unwrap();
+        let world = world.unwrap();
+        assert_eq!((world.width(), world.height()), (5, 5));
+        assert_eq!(
+            world.live_coords(),
+            [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip_keeps_the_cells_and_rule() {
+        let rule = Rule {
+            include_center: true,
+            ..Rule::parse("B36/S23").unwrap()
+        };
+        let mut world = World::new(9, 6, 1, rule);
+        world.stamp(&patterns::glider(), (7, 4), State::ALIVE);
+
+        let json = serde_json::to_string(&world).unwrap();
+        let copy: World = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.rule, world.rule);
+        assert_eq!(copy.live_coords(), world.live_coords());
+        assert_eq!(copy.gutter(), 0);
+
+        let truncated = json.replacen("\"DEAD\",", "", 1);
+        assert!(serde_json::from_str::<World>(&truncated).is_err());
+    }
+
+    #[test]
+    fn edits_and_generations_are_undone_and_redone() {
+        let mut world = World::new(6, 6, 0, Rule::default());
+        world.checkpoint();
+        world.stamp(&patterns::blinker(), (1, 2), State::ALIVE);
+        let horizontal = world.live_cells();
+        world.checkpoint();
+        world.freeze(world.index(2, 2));
+        world.step();
+        let stepped = world.live_cells();
+        assert_ne!(stepped, horizontal);
+
+        assert!(world.undo());
+        assert_eq!(world.live_cells(), horizontal);
+        assert_eq!(world.generation(), 0);
+        assert!(world.is_frozen(world.index(2, 2)));
+        assert!(world.undo());
+        assert!(!world.is_frozen(world.index(2, 2)));
+        assert!(world.undo());
+        assert!(world.is_empty());
+        assert!(!world.undo());
+
+        assert!(world.redo());
+        assert!(world.redo());
+        assert!(world.redo());
+        assert_eq!(world.live_cells(), stepped);
+        assert_eq!(world.generation(), 1);
+        assert!(!world.redo());
+
+        world.undo();
+        world.checkpoint();
+        assert!(!world.redo());
+    }
+
+    #[test]
+    fn undo_keeps_the_last_checkpoints_only() {
+        let mut world = World::new(4, 4, 0, Rule::default());
+        world.set_undo_limit(3);
+        for index in 0..5 {
+            world.checkpoint();
+            world.set_cell_state(index, State::ALIVE);
+        }
+
+        let mut undone = 0;
+        while world.undo() {
+            undone += 1;
+        }
+        assert_eq!(undone, 3);
+        assert_eq!(world.live_cells(), vec![0, 1]);
+
+        let copy = world.clone();
+        assert_eq!(copy.undo_limit(), 3);
+        world.set_undo_limit(0);
+        world.checkpoint();
+        assert!(!world.undo());
+    }
+
+    #[test]
+    fn trimming_crops_a_glider_to_its_bounding_box() {
+        let mut world = World::new(100, 80, 1, Rule::default());
+        world.stamp(&patterns::glider(), (41, 27), State::ALIVE);
+        let glider = world.live_coords();
+
+        let mut tight = world.clone();
+        tight.trim(0);
+        assert_eq!((tight.width(), tight.height(), tight.gutter()), (3, 3, 1));
+        assert_eq!(
+            tight.live_coords(),
+            glider
+                .iter()
+                .map(|&(x, y)| (x - 41, y - 27))
+                .collect::<Vec<_>>()
+        );
+
+        world.trim(2);
+        assert_eq!((world.width(), world.height()), (7, 7));
+        assert_eq!(
+            world.live_coords(),
+            glider
+                .iter()
+                .map(|&(x, y)| (x - 39, y - 25))
+                .collect::<Vec<_>>()
+        );
+
+        let mut empty = World::new(10, 10, 0, Rule::default());
+        empty.trim(1);
+        assert_eq!((empty.width(), empty.height()), (10, 10));
+    }
+
+    #[test]
+    fn resizing_keeps_the_cells_aligned_on_the_top_left() {
+        let mut world = World::new(8, 6, 0, Rule::default());
+        world.stamp(&patterns::glider(), (0, 0), State::ALIVE);
+        world.stamp(&patterns::block(), (6, 4), State::ALIVE);
+
+        world.resize(12, 9);
+        assert_eq!((world.width(), world.height()), (12, 9));
+        assert_eq!(world.live_coords(), {
+            let mut coords = vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+            coords.extend(vec![(6, 4), (7, 4), (6, 5), (7, 5)]);
+            coords.sort_by_key(|&(x, y)| (y, x));
+            coords
+        });
+        // The neighbours follow the new size: the block no longer wraps
+        assert_eq!(world.alive_neighbours(world.index(0, 4)), 0);
+
+        world.resize(5, 5);
+        assert_eq!(
+            world.live_coords(),
+            vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+        );
+        world.step();
+        assert_eq!(world.population(), 5);
+    }
+
+    #[test]
+    fn electrons_travel_along_wires() {
+        let mut world = World::new(8, 3, 0, Rule::default());
+        world.automaton = Automaton::Wireworld;
+        world.set_boundary(Boundary::Fixed);
+        let wire: Vec<_> = (0..8).map(|x| (x, 1)).collect();
+        world.stamp(&wire, (0, 0), State::CONDUCTOR);
+        world.stamp(&[(0, 1)], (0, 0), State::TAIL);
+        world.stamp(&[(1, 1)], (0, 0), State::HEAD);
+
+        for head in 2..8 {
+            world.step();
+            let row: Vec<_> = (0..8)
+                .map(|x| world.cell_state(world.index(x, 1)).unwrap())
+                .collect();
+            for (x, &state) in row.iter().enumerate() {
+                let expected = match x {
+                    x if x == head => State::HEAD,
+                    x if x + 1 == head => State::TAIL,
+                    _ => State::CONDUCTOR,
+                };
+                assert_eq!(state, expected, "cell {} at generation {}", x, head - 1);
+            }
+        }
+        // Off the wire, nothing changes
+        assert!((0..8).all(|x| world.cell_state(world.index(x, 0)) == Some(State::DEAD)));
+    }
+
+    #[test]
+    fn brians_brain_cells_turn_on_die_and_turn_off() {
+        let mut world = World::new(7, 7, 0, Rule::default());
+        world.automaton = Automaton::BriansBrain;
+        world.set_boundary(Boundary::Fixed);
+        world.stamp(&[(2, 3), (4, 3)], (0, 0), State::ALIVE);
+        let (seeded, parent) = (world.index(3, 3), world.index(2, 3));
+
+        let mut states = Vec::new();
+        for _ in 0..3 {
+            states.push((world.cell_state(seeded), world.cell_state(parent)));
+            world.step();
+        }
+        assert_eq!(
+            states,
+            [
+                (Some(State::DEAD), Some(State::ALIVE)),
+                (Some(State::ALIVE), Some(State::DYING)),
+                (Some(State::DYING), Some(State::DEAD)),
+            ]
+        );
+        assert_eq!(world.cell_state(seeded), Some(State::DEAD));
+    }
+
+    #[test]
+    fn conway_as_a_custom_rule_matches_the_life_like_rule() {
+        let mut life = World::new(23, 17, 1, Rule::default());
+        life.stamp(
+            &patterns::soup(23, 17, 0.4, &mut Rng::new(3)),
+            (0, 0),
+            State::ALIVE,
+        );
+        let mut sequential = life.clone();
+        sequential.automaton = Automaton::Custom(Arc::new(ConwayRule));
+        let mut parallel = sequential.clone();
+
+        for _ in 0..12 {
+            life.step();
+            sequential.update_sequential();
+            parallel.update_parallel();
+            assert_eq!(sequential.live_cells(), life.live_cells());
+            assert_eq!(parallel.live_cells(), life.live_cells());
+        }
+    }
+
+    #[test]
+    fn custom_rules_see_the_states_of_the_neighbourhood() {
+        /// Alive next to any conductor, each neighbourhood being checked to
+        /// hold as many states as the neighbourhood has cells.
+        #[derive(Debug)]
+        struct NextToWire(usize);
+
+        impl CellRule for NextToWire {
+            fn next_state(&self, _: State, neighbour_states: &[State]) -> State {
+                assert_eq!(neighbour_states.len(), self.0);
+                State::from_alive(neighbour_states.contains(&State::CONDUCTOR))
+            }
+        }
+
+        let mut world = World::new(7, 7, 0, Rule::default());
+        world.set_neighbourhood(Neighbourhood::VonNeumann);
+        world.automaton = Automaton::Custom(Arc::new(NextToWire(4)));
+        world.stamp(&[(3, 3)], (0, 0), State::CONDUCTOR);
+        world.step();
+
+        assert_eq!(
+            world.live_cells(),
+            [(3, 2), (2, 3), (4, 3), (3, 4)]
+                .iter()
+                .map(|&(x, y)| world.index(x, y))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn immutable_cells_never_change() {
+        let automata = [
+            Automaton::LifeLike,
+            Automaton::Wireworld,
+            Automaton::BriansBrain,
+            Automaton::Custom(Arc::new(ConwayRule)),
+        ];
+        for automaton in automata.iter() {
+            let mut world = World::new(5, 5, 0, Rule::default());
+            world.automaton = automaton.clone();
+            // Born under B3 or next to two on cells, kept under S3
+            world.stamp(&[(1, 1), (3, 1), (1, 3)], (0, 0), State::ALIVE);
+            world.stamp(&[(2, 1)], (0, 0), State::HEAD);
+            world.set_cell_state(world.index(2, 2), State::IMMUTABLE);
+
+            for _ in 0..4 {
+                world.step();
+                assert_eq!(
+                    world.cell_state(world.index(2, 2)),
+                    Some(State::IMMUTABLE),
+                    "{:?}",
+                    automaton
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gliders_cannot_cross_a_ring_of_walls() {
+        let mut world = World::new(20, 20, 0, Rule::default());
+        let ring: Vec<_> = (0..10)
+            .flat_map(|i| vec![(i, 0), (i, 9), (0, i), (9, i)])
+            .collect();
+        world.stamp(&ring, (2, 2), State::IMMUTABLE);
+        world.stamp(&patterns::glider(), (4, 4), State::ALIVE);
+
+        let mut reached_the_wall = false;
+        for _ in 0..80 {
+            world.step();
+            for index in world.live_cells() {
+                let (x, y) = utils::index_to_coords(index, 20);
+                assert!(
+                    (3..11).contains(&x) && (3..11).contains(&y),
+                    "({}, {})",
+                    x,
+                    y
+                );
+                reached_the_wall |= x == 10 || y == 10;
+            }
+        }
+        assert!(reached_the_wall);
+        for &(x, y) in &ring {
+            assert_eq!(
+                world.cell_state(world.index(x + 2, y + 2)),
+                Some(State::IMMUTABLE)
+            );
+        }
+    }
+
+    #[test]
+    fn glider_hitting_a_wall_settles_into_a_block() {
+        let mut world = World::new(16, 16, 0, Rule::default());
+        let wall: Vec<_> = (0..16).map(|x| (x, 12)).collect();
+        world.stamp(&wall, (0, 0), State::IMMUTABLE);
+        world.stamp(&patterns::glider(), (2, 2), State::ALIVE);
+
+        // Walls count as dead: the glider collides as with the edge of a
+        // bounded world, leaving a block against the wall
+        for _ in 0..32 {
+            world.step();
+        }
+        let block = vec![(10, 10), (11, 10), (10, 11), (11, 11)];
+        assert_eq!(world.live_coords(), block);
+        world.step();
+        assert_eq!(world.live_coords(), block);
+        assert!((0..16).all(|x| world.cell_state(world.index(x, 12)) == Some(State::IMMUTABLE)));
+    }
+
+    #[test]
+    fn ages_count_the_generations_alive_across_a_blinker_cycle() {
+        let mut world = World::new(5, 5, 0, Rule::default());
+        world.stamp(&[(1, 2), (2, 2), (3, 2)], (0, 0), State::ALIVE);
+        let (center, end, side) = (world.index(2, 2), world.index(1, 2), world.index(2, 1));
+
+        let mut ages = Vec::new();
+        for _ in 0..3 {
+            ages.push([world.age(center), world.age(end), world.age(side)]);
+            world.step();
+        }
+        assert_eq!(
+            ages,
+            [
+                [Some(0), Some(0), Some(0)],
+                [Some(1), Some(0), Some(0)],
+                [Some(2), Some(0), Some(0)],
+            ]
+        );
+        assert_eq!(world.age(center), Some(3));
+
+        // Age only changes the colors when enabled
+        let theme = Theme::default();
+        let mut frame = vec![0; 5 * 5 * 4];
+        world.draw(&mut frame, &DrawOptions::default());
+        assert_eq!(&frame[center * 4..center * 4 + 4], theme.alive);
+        let options = DrawOptions {
+            age_colors: true,
+            ..DrawOptions::default()
+        };
+        world.draw(&mut frame, &options);
+        assert_eq!(&frame[center * 4..center * 4 + 4], theme.aged(3));
+        assert_ne!(theme.aged(3), theme.alive);
+    }
+
+    #[test]
+    fn trails_count_down_after_death() {
+        let mut world = World::new(5, 5, 0, Rule::default());
+        let lonely = world.index(2, 2);
+        world.set_cell_state(lonely, State::ALIVE);
+        assert_eq!(world.cells[lonely].trail, 0);
+
+        for elapsed in 0..4 {
+            world.step();
+            assert_eq!(world.cells[lonely].trail, MAX_TRAIL - elapsed);
+        }
+
+        // Trails are only drawn when enabled, fading with the generations
+        let theme = Theme::default();
+        let mut frame = vec![0; 5 * 5 * 4];
+        world.draw(&mut frame, &DrawOptions::default());
+        assert_eq!(&frame[lonely * 4..lonely * 4 + 4], theme.dead);
+        let options = DrawOptions {
+            trail: 8,
+            ..DrawOptions::default()
+        };
+        world.draw(&mut frame, &options);
+        assert_eq!(&frame[lonely * 4..lonely * 4 + 4], theme.trail(5, 8));
+        for _ in 0..5 {
+            world.step();
+        }
+        world.draw(&mut frame, &options);
+        assert_eq!(&frame[lonely * 4..lonely * 4 + 4], theme.dead);
+    }
+
+    #[test]
+    fn still_lifes_and_blinkers_settle() {
+        let mut block = World::new(6, 6, 0, Rule::default());
+        block.stamp(&[(0, 0), (1, 0), (0, 1), (1, 1)], (2, 2), State::ALIVE);
+        assert_eq!(block.settled_period(), None);
+        block.step();
+        assert!(block.is_stable());
+        assert_eq!(block.settled_period(), Some(1));
+
+        let mut blinker = World::new(6, 6, 0, Rule::default());
+        blinker.stamp(&[(1, 2), (2, 2), (3, 2)], (0, 0), State::ALIVE);
+        blinker.step();
+        assert_eq!(blinker.settled_period(), None);
+        blinker.step();
+        assert!(!blinker.is_stable());
+        assert_eq!(blinker.settled_period(), Some(2));
+
+        let mut glider = World::new(12, 12, 0, Rule::default());
+        glider.stamp(&patterns::glider(), (0, 0), State::ALIVE);
+        for _ in 0..8 {
+            glider.step();
+            assert_eq!(glider.settled_period(), None);
+        }
+    }
+
+    #[test]
+    fn thin_worlds_count_each_neighbour_once() {
+        for &(width, height) in &[(1, 5), (5, 1), (1, 1), (2, 2)] {
+            let mut world = World::new(width, height, 0, Rule::default());
+            for index in 0..world.cell_count() {
+                let mut neighbours = world.neighbours_of(index).to_vec();
+                neighbours.sort_unstable();
+                neighbours.dedup();
+                assert_eq!(neighbours.len(), world.neighbours_of(index).len());
+                assert!(!neighbours.contains(&index), "{}x{}", width, height);
+            }
+
+            for index in 0..world.cell_count() {
+                world.set_cell_state(index, State::ALIVE);
+            }
+            // The Moore neighbourhood covers up to 3 cells along each axis
+            let expected = width.min(3) * height.min(3) - 1;
+            for index in 0..world.cell_count() {
+                assert_eq!(
+                    world.alive_neighbours(index),
+                    expected,
+                    "{}x{}",
+                    width,
+                    height
+                );
+            }
+        }
+    }
+
+    /// Next generation of Conway's Game of Life on a toroidal `width` wide
+    /// grid, computed cell by cell without any of the buffers of `World`.
+    fn reference_life_step(alive: &[bool], width: usize) -> Vec<bool> {
+        let height = alive.len() / width;
+        (0..alive.len())
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                let mut neighbours = 0;
+                for dy in [height - 1, 0, 1].iter() {
+                    for dx in [width - 1, 0, 1].iter() {
+                        if (*dx, *dy) != (0, 0) {
+                            let (nx, ny) = ((x + dx) % width, (y + dy) % height);
+                            neighbours += alive[ny * width + nx] as usize;
+                        }
+                    }
+                }
+                matches!((alive[index], neighbours), (true, 2) | (_, 3))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn updates_match_a_reference_implementation() {
+        let (width, height) = (37, 29);
+        let mut sequential = World::new(width, height, 0, Rule::default());
+        let soup = patterns::soup(width, height, 0.4, &mut Rng::new(11));
+        sequential.stamp(&soup, (0, 0), State::ALIVE);
+        let mut parallel = sequential.clone();
+        let mut reference: Vec<bool> = (0..width * height)
+            .map(|index| sequential.cell_state(index) == Some(State::ALIVE))
+            .collect();
+
+        for generation in 1..=60 {
+            reference = reference_life_step(&reference, width);
+            sequential.update_sequential();
+            parallel.update_parallel();
+            let expected: Vec<usize> = (0..reference.len()).filter(|&i| reference[i]).collect();
+            assert_eq!(
+                sequential.live_cells(),
+                expected,
+                "generation {}",
+                generation
+            );
+            assert_eq!(parallel.live_cells(), expected, "generation {}", generation);
+        }
+    }
+
+    #[test]
+    fn live_cell_queries_are_deterministic() {
+        let mut world = World::new(400, 300, 2, Rule::default());
+        world.stamp(
+            &patterns::soup(400, 300, 0.3, &mut Rng::new(5)),
+            (0, 0),
+            State::ALIVE,
+        );
+        let expected: Vec<usize> = (0..world.cell_count())
+            .filter(|&index| world.cell_state(index) == Some(State::ALIVE))
+            .collect();
+
+        for _ in 0..10 {
+            assert_eq!(world.live_cells(), expected);
+            assert_eq!(world.first_live_cell(), expected.first().copied());
+        }
+        assert_eq!(World::new(8, 8, 0, Rule::default()).first_live_cell(), None);
+    }
+
+    #[test]
+    fn alive_neighbours_of_a_blinker() {
+        let mut world = World::new(5, 5, 0, Rule::default());
+        world.stamp(&[(1, 2), (2, 2), (3, 2)], (0, 0), State::ALIVE);
+
+        let counts: Vec<Vec<usize>> = (0..5)
+            .map(|y| {
+                (0..5)
+                    .map(|x| world.alive_neighbours(world.index(x, y)))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                [0, 0, 0, 0, 0],
+                [1, 2, 3, 2, 1],
+                [1, 1, 2, 1, 1],
+                [1, 2, 3, 2, 1],
+                [0, 0, 0, 0, 0],
+            ]
+        );
+        assert_eq!(world.alive_neighbours(world.cell_count()), 0);
+    }
+
+    #[test]
+    fn step_until_stops_when_the_predicate_holds() {
+        let mut world = World::new(40, 40, 0, Rule::default());
+        world.stamp(
+            &[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)],
+            (18, 18),
+            State::ALIVE,
+        );
+        let mut replayed = world.clone();
+
+        // The R-pentomino grows from 5 cells past 10 within a few generations
+        let reached = world.step_until(100, |world| world.population() > 10);
+        let generation = reached.unwrap();
+        assert_eq!(world.generation() as usize, generation);
+        assert!(world.population() > 10);
+        for _ in 1..generation {
+            replayed.step();
+            assert!(replayed.population() <= 10);
+        }
+
+        let mut block = World::new(8, 8, 0, Rule::default());
+        block.stamp(&patterns::block(), (3, 3), State::ALIVE);
+        assert_eq!(block.step_until(20, |world| world.population() != 4), None);
+        assert_eq!(block.generation(), 20);
+    }
+
+    #[test]
+    fn regions_follow_their_own_rule() {
+        let mut world = World::new(40, 20, 0, Rule::parse("B3/S23").unwrap());
+        let highlife = Rule::parse("B36/S23").unwrap();
+        let right = Rect {
+            x: 20,
+            y: 0,
+            width: 20,
+            height: 20,
+        };
+        world.region_rules.push((right, highlife));
+        // The middle cell of both has 6 neighbours, born under B6 only
+        let rows = [(0, 0), (1, 0), (2, 0), (0, 2), (1, 2), (2, 2)];
+        world.stamp(&rows, (5, 5), State::ALIVE);
+        world.stamp(&rows, (25, 5), State::ALIVE);
+        world.step();
+
+        assert_eq!(world.cell_state(world.index(6, 6)), Some(State::DEAD));
+        assert_eq!(world.cell_state(world.index(26, 6)), Some(State::ALIVE));
+    }
+
+    #[test]
+    fn worlds_are_empty_until_a_cell_is_set() {
+        let mut world = World::new(12, 9, 1, Rule::default());
+        world.stamp(&patterns::glider(), (3, 3), State::ALIVE);
+        world.clear();
+        assert!(world.is_empty());
+
+        world.set_cell_state(world.index(11, 8), State::ALIVE);
+        assert!(!world.is_empty());
+        world.set_cell_state(world.index(11, 8), State::IMMUTABLE);
+        assert!(world.is_empty());
+    }
+
+    #[test]
+    fn disc_masks_confine_patterns_to_the_disc() {
+        let mut world = World::new(30, 30, 0, Rule::default());
+        world.stamp(
+            &patterns::soup(30, 30, 0.5, &mut Rng::new(9)),
+            (0, 0),
+            State::ALIVE,
+        );
+        world.set_active_mask(Some(world.disc_mask()));
+        assert!(world.is_active(world.index(15, 15)));
+        assert!(!world.is_active(world.index(0, 0)) && !world.is_active(world.index(29, 29)));
+        assert!(world
+            .live_cells()
+            .iter()
+            .all(|&index| world.is_active(index)));
+
+        for _ in 0..60 {
+            world.step();
+            assert!(world
+                .live_cells()
+                .iter()
+                .all(|&index| world.is_active(index)));
+        }
+
+        world.set_active_mask(None);
+        assert!(world.is_active(world.index(0, 0)));
+    }
+
+    #[test]
+    fn cycling_visits_every_built_in_automaton() {
+        let mut world = World::new(6, 6, 0, Rule::default());
+        world.stamp(&patterns::blinker(), (1, 2), State::ALIVE);
+        let cells = world.live_cells();
+
+        let mut names = vec![world.automaton_name()];
+        for _ in 0..builtin_automata().len() {
+            world.cycle_automaton();
+            names.push(world.automaton_name());
+        }
+        assert_eq!(names.first(), names.last());
+        let mut seen = names[1..].to_vec();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), builtin_automata().len());
+        assert_eq!(world.live_cells(), cells);
+    }
+
+    #[test]
+    fn tables_run_brians_brain_and_wireworld_in_a_world() {
+        let mut brain = World::new(24, 24, 0, Rule::default());
+        brain.stamp(
+            &patterns::soup(24, 24, 0.3, &mut Rng::new(8)),
+            (0, 0),
+            State::ALIVE,
+        );
+        brain.automaton = Automaton::BriansBrain;
+        // The table of the `TableRule` documentation, and the one also
+        // turning Wireworld cells off
+        let documented = TableRule::parse("states 3\n0 1:2 -> 1\n1 -> 2\n2 -> 0").unwrap();
+        for rule in [documented, TableRule::brians_brain()] {
+            let mut brain = brain.clone();
+            let mut table = brain.clone();
+            table.automaton = Automaton::Table(rule);
+
+            for generation in 1..=12 {
+                brain.step();
+                table.step();
+                assert_eq!(table.states, brain.states, "generation {}", generation);
+            }
+            assert!(table.states.contains(&State::DYING));
+        }
+
+        // A loop of wire with an electron running around it, next to a
+        // painted alive cell and a wall
+        let mut wireworld = World::new(8, 6, 0, Rule::default());
+        let wire: Vec<_> = (1..7)
+            .flat_map(|x| vec![(x, 1), (x, 4)])
+            .chain((2..4).flat_map(|y| vec![(1, y), (6, y)]))
+            .collect();
+        wireworld.stamp(&wire, (0, 0), State::CONDUCTOR);
+        wireworld.set_cell_state(wireworld.index(2, 1), State::HEAD);
+        wireworld.set_cell_state(wireworld.index(1, 1), State::TAIL);
+        wireworld.set_cell_state(wireworld.index(7, 1), State::ALIVE);
+        wireworld.set_cell_state(wireworld.index(0, 0), State::IMMUTABLE);
+        wireworld.automaton = Automaton::Wireworld;
+        let mut table = wireworld.clone();
+        table.automaton = Automaton::Table(TableRule::wireworld());
+
+        for generation in 1..=20 {
+            wireworld.step();
+            table.step();
+            assert_eq!(table.states, wireworld.states, "generation {}", generation);
+        }
+        assert!(table.states.contains(&State::HEAD));
+    }
+
+    #[test]
+    fn block_tiles_the_world() {
+        let mut world = World::new(6, 6, 0, Rule::default());
+        world.tile_pattern(&patterns::block(), 3, 3);
+
+        assert_eq!(
+            world.live_coords(),
+            [
+                (0, 0),
+                (1, 0),
+                (3, 0),
+                (4, 0),
+                (0, 1),
+                (1, 1),
+                (3, 1),
+                (4, 1),
+                (0, 3),
+                (1, 3),
+                (3, 3),
+                (4, 3),
+                (0, 4),
+                (1, 4),
+                (3, 4),
+                (4, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn uniform_world_has_no_entropy() {
+        let mut world = World::new(16, 16, 0, Rule::default());
+        assert_eq!(world.entropy(), 0.0);
+        assert_eq!(world.block_entropy(2), 0.0);
+
+        world.randomize_seeded(0.5, 1);
+        assert!(world.entropy() > 1.0);
+        assert!(world.block_entropy(2) > 1.0);
+    }
+
+    #[test]
+    fn domino_is_still_when_cells_count_themselves() {
+        let domino = [(0, 0), (1, 0)];
+        let mut outer = World::new(6, 6, 0, Rule::default());
+        outer.stamp(&domino, (2, 2), State::ALIVE);
+        let mut inner = outer.clone();
+        inner.rule.include_center = true;
+
+        // Each cell has one alive neighbour: too few to survive in Life, but
+        // counting themselves they have the two of a survival
+        outer.step();
+        inner.step();
+        assert!(outer.is_empty());
+        assert_eq!(inner.live_coords(), [(2, 2), (3, 2)]);
+    }
+
+    /// Rule undoing each block replacement of `rule`, which must be
+    /// reversible.
+    fn inverse(rule: &BlockRule) -> BlockRule {
+        let mut table = [0; 16];
+        for (block, &next) in rule.table.iter().enumerate() {
+            table[next as usize] = block as u8;
+        }
+        BlockRule { table }
+    }
+
+    #[test]
+    fn margolus_automata_run_backward() {
+        for rule in [BlockRule::critters(), BlockRule::billiard_ball()] {
+            assert!(rule.is_reversible());
+            let mut world = World::new(12, 12, 0, Rule::default());
+            world.randomize_seeded(0.3, 5);
+            let start = world.live_cells();
+
+            world.automaton = Automaton::Margolus(rule);
+            for _ in 0..10 {
+                world.step();
+            }
+            assert_ne!(world.live_cells(), start);
+
+            // Each generation is undone on the partition it was computed on
+            world.automaton = Automaton::Margolus(inverse(&rule));
+            for generation in (0..10).rev() {
+                world.restore(&Snapshot {
+                    generation,
+                    ..world.snapshot()
+                });
+                world.step();
+            }
+            assert_eq!(world.live_cells(), start);
+        }
+    }
+
+    #[test]
+    fn table_conway_matches_life() {
+        let conway = TableRule::parse("states 2\n0 1:3 -> 1\n1 1:23 -> 1\n1 -> 0").unwrap();
+        let mut life = World::new(24, 24, 0, Rule::default());
+        life.randomize_seeded(0.4, 42);
+        let mut table = life.clone();
+        table.automaton = Automaton::Table(conway);
+
+        for _ in 0..50 {
+            life.step();
+            table.step();
+            assert_eq!(table.live_cells(), life.live_cells());
+        }
+    }
+
+    #[test]
+    fn cells_are_drawn_with_the_colors_of_their_state() {
+        let mut world = World::new(4, 2, 1, Rule::default());
+        let states = [
+            State::ALIVE,
+            State::DEAD,
+            State::CONDUCTOR,
+            State::HEAD,
+            State::TAIL,
+            State::DYING,
+            State::IMMUTABLE,
+        ];
+        for (i, &state) in states.iter().enumerate() {
+            world.set_cell_state(world.index(i % 4, i / 4), state);
+        }
+        let mut frame = vec![0; 4 * 2 * 4];
+        world.draw(&mut frame, &DrawOptions::default());
+
+        let theme = Theme::default();
+        let expected = [
+            theme.alive,
+            theme.dead,
+            theme.conductor,
+            theme.head,
+            theme.tail,
+            theme.dying,
+            theme.immutable,
+            theme.dead,
+        ];
+        for (pixel, rgba) in frame.chunks_exact(4).zip(expected.iter()) {
+            assert_eq!(pixel, rgba);
+        }
+    }
+
+    #[test]
+    fn seams_tint_the_edges_only() {
+        let world = World::new(4, 3, 1, Rule::default());
+        let options = DrawOptions {
+            seams: true,
+            ..DrawOptions::default()
+        };
+        let mut frame = vec![0; 4 * 3 * 4];
+        world.draw(&mut frame, &options);
+
+        let theme = Theme::default();
+        for (i, pixel) in frame.chunks_exact(4).enumerate() {
+            let (x, y) = (i % 4, i / 4);
+            let expected = if (1..3).contains(&x) && y == 1 {
+                theme.dead
+            } else {
+                theme.seam(theme.dead)
+            };
+            assert_eq!(pixel, expected, "({}, {})", x, y);
+        }
+        assert_ne!(theme.seam(theme.dead), theme.dead);
+    }
+
+    #[test]
+    fn cursor_highlights_a_single_cell() {
+        let mut world = World::new(3, 3, 1, Rule::default());
+        let cursor = world.index(2, 1);
+        world.set_cell_state(cursor, State::ALIVE);
+        let options = DrawOptions {
+            cursor: Some(cursor),
+            ..DrawOptions::default()
+        };
+        let mut frame = vec![0; 3 * 3 * 4];
+        world.draw(&mut frame, &options);
+
+        let theme = Theme::default();
+        let highlighted: Vec<usize> = (0..9)
+            .filter(|&i| frame[i * 4..i * 4 + 4] != theme.dead)
+            .collect();
+        assert_eq!(highlighted, [5]);
+        assert_eq!(&frame[20..24], theme.highlighted(theme.alive));
+    }
+
+    #[test]
+    fn dead_cells_can_be_drawn_transparent() {
+        let mut world = World::new(2, 1, 0, Rule::default());
+        world.set_cell_state(0, State::ALIVE);
+        let mut frame = vec![0; 2 * 4];
+        let options = DrawOptions {
+            transparent_dead: true,
+            ..DrawOptions::default()
+        };
+        world.draw(&mut frame, &options);
+
+        assert_eq!(&frame[..4], Theme::default().alive);
+        assert_eq!(&frame[4..], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn separate_blinkers_are_two_components() {
+        let mut world = World::new(12, 12, 0, Rule::default());
+        world.stamp(&patterns::blinker(), (1, 1), State::ALIVE);
+        world.stamp(&patterns::blinker(), (6, 6), State::ALIVE);
+        assert_eq!(world.connected_components(), 2);
+    }
+
+    #[test]
+    fn components_connect_across_the_seam() {
+        let mut world = World::new(10, 10, 0, Rule::default());
+        world.stamp(&[(0, 0), (1, 0), (2, 0)], (8, 5), State::ALIVE);
+        assert_eq!(world.connected_components(), 1);
+
+        world.set_boundary(Boundary::Fixed);
+        assert_eq!(world.connected_components(), 2);
+    }
+
+    #[test]
+    fn population_never_exceeds_the_cap() {
+        let mut world = World::new(32, 32, 0, Rule::parse("B3/S23").unwrap());
+        world.randomize_seeded(0.1, 3);
+        world.max_population = Some(world.population() + 10);
+
+        for _ in 0..50 {
+            world.step();
+            assert!(world.population() <= world.max_population.unwrap());
+        }
+    }
+
+    #[test]
+    fn capped_births_drop_the_lowest_indexes_first() {
+        let mut world = World::new(6, 6, 0, Rule::default());
+        world.stamp(&patterns::blinker(), (1, 2), State::ALIVE);
+        world.max_population = Some(2);
+        world.step();
+
+        // Of the births above and below the center, the one above goes
+        assert_eq!(world.live_coords(), [(2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn payloads_count_the_generations_alive() {
+        let mut world: World<u32> = World::with_payloads(6, 6, 0, Rule::default());
+        world.payload_rule = Some(|payload, state, _| payload + (state == State::ALIVE) as u32);
+        world.stamp(&patterns::blinker(), (1, 2), State::ALIVE);
+
+        for parallelism in [Parallelism::Sequential, Parallelism::Parallel] {
+            world.parallelism = parallelism;
+            for _ in 0..3 {
+                world.step();
+            }
+        }
+
+        // The center of the blinker is always alive, its ends every other
+        // generation
+        assert_eq!(world.payload(world.index(2, 2)), Some(6));
+        assert_eq!(world.payload(world.index(1, 2)), Some(3));
+        assert_eq!(world.payload(world.index(2, 1)), Some(3));
+        assert_eq!(world.payload(world.index(0, 0)), Some(0));
+    }
+
+    #[test]
+    fn payloads_read_their_neighbours() {
+        let mut world: World<u32> = World::with_payloads(7, 1, 0, Rule::default());
+        world.set_boundary(Boundary::Fixed);
+        world.payload_rule =
+            Some(|payload, _, neighbours| neighbours.iter().copied().fold(payload, u32::max));
+        world.set_payload(3, 5);
+
+        for parallelism in [Parallelism::Sequential, Parallelism::Parallel] {
+            world.parallelism = parallelism;
+            world.step();
+        }
+        let payloads: Vec<u32> = (0..7).map(|index| world.payload(index).unwrap()).collect();
+        assert_eq!(payloads, [0, 5, 5, 5, 5, 5, 0]);
+    }
+
+    #[test]
+    fn applied_changes_match_the_stepped_world() {
+        let mut world = World::new(16, 16, 0, Rule::default());
+        world.randomize_seeded(0.4, 9);
+        world.freeze(world.index(3, 3));
+        let mut copy = world.clone();
+
+        for _ in 0..20 {
+            world.step();
+            copy.apply_changes(&world.changes());
+            assert_eq!(copy.snapshot(), world.snapshot());
+            assert_eq!(copy.changed_indices(), world.changed_indices());
+            assert_eq!(copy.settled_period(), world.settled_period());
+        }
+        let options = DrawOptions {
+            age_colors: true,
+            trail: MAX_TRAIL,
+            ..DrawOptions::default()
+        };
+        let (mut drawn, mut copy_drawn) = (vec![0; 16 * 16 * 4], vec![0; 16 * 16 * 4]);
+        world.draw(&mut drawn, &options);
+        copy.draw(&mut copy_drawn, &options);
+        assert_eq!(copy_drawn, drawn);
+    }
+
+    #[test]
+    fn binary_round_trip_is_lossless() {
+        let mut world = World::new(1000, 1000, 0, Rule::parse("B36/S23").unwrap());
+        world.randomize_seeded(0.5, 11);
+        let mut bytes = Vec::new();
+        world.save_binary(&mut bytes).unwrap();
+
+        // 16 bytes of header and the 7 of the rule
+        assert_eq!(bytes.len(), 16 + 7 + 1000 * 1000 / 8);
+        let loaded = World::load_binary(&bytes[..]).unwrap();
+        assert_eq!(loaded.rule, world.rule);
+        assert_eq!(loaded.live_cells(), world.live_cells());
+    }
+
+    #[test]
+    fn binary_worlds_too_large_are_refused() {
+        let mut bytes = Vec::new();
+        World::new(2, 2, 0, Rule::default())
+            .save_binary(&mut bytes)
+            .unwrap();
+        // Width and height set to `u32::MAX`
+        bytes[6..14].copy_from_slice(&[0xFF; 8]);
+
+        assert!(matches!(
+            World::load_binary(&bytes[..]),
+            Err(BinaryError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn multi_state_worlds_are_not_saved_as_bits() {
+        let mut world = World::new(4, 4, 0, Rule::default());
+        world.set_cell_state(0, State::HEAD);
+        assert!(world.save_binary(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn dead_cells_are_drawn_opaque() {
+        let world = World::new(3, 2, 0, Rule::default());
+        let mut frame = vec![0; 3 * 2 * 4];
+        world.draw(&mut frame, &DrawOptions::default());
+
+        for pixel in frame.chunks_exact(4) {
+            assert_eq!(pixel, Theme::default().dead);
+            assert_eq!(pixel[3], 0xFF);
         }
     }
 }
@@ -0,0 +1,125 @@
+use crate::automata::utils;
+use rayon::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloatRule {
+    /// A cell takes the mean value of its neighbourhood.
+    Smooth,
+    /// A cell becomes `1.0` when the mean value of its neighbourhood reaches
+    /// the threshold, `0.0` otherwise.
+    Threshold(f32),
+}
+
+/// A world of continuous cells holding values in `[0, 1]`, updated from the
+/// mean value of the square neighbourhood of radius `radius` around each cell
+/// (the cell itself included), Larger than Life style.
+pub struct FloatWorld {
+    pub paused: bool,
+    pub rule: FloatRule,
+    width: usize,
+    height: usize,
+    radius: usize,
+    values: Vec<f32>,
+}
+
+impl FloatWorld {
+    pub fn new(width: usize, height: usize, radius: usize, rule: FloatRule) -> Self {
+        Self {
+            paused: true,
+            rule,
+            width,
+            height,
+            radius,
+            values: vec![0.0; width * height],
+        }
+    }
+
+    pub fn value(&self, index: usize) -> Option<f32> {
+        self.values.get(index).copied()
+    }
+
+    pub fn set_value(&mut self, index: usize, value: f32) {
+        if let Some(cell) = self.values.get_mut(index) {
+            *cell = value.clamp(0.0, 1.0);
+        };
+    }
+
+    fn neighbourhood_mean(&self, index: usize) -> f32 {
+        let (x, y) = utils::index_to_coords(index, self.width);
+        let radius = self.radius as isize;
+        let mut sum = 0.0;
+
+        for dy in -radius..=radius {
+            let y = (y as isize + dy).rem_euclid(self.height as isize) as usize;
+            for dx in -radius..=radius {
+                let x = (x as isize + dx).rem_euclid(self.width as isize) as usize;
+                sum += self.values[utils::coords_to_index(x, y, self.width)];
+            }
+        }
+
+        let side = 2 * self.radius + 1;
+        sum / (side * side) as f32
+    }
+
+    pub fn update(&mut self) {
+        if self.paused {
+            return;
+        }
+
+        self.values = (0..self.values.len())
+            .into_par_iter()
+            .map(|index| {
+                let mean = self.neighbourhood_mean(index);
+
+                match self.rule {
+                    FloatRule::Smooth => mean,
+                    FloatRule::Threshold(threshold) if mean >= threshold => 1.0,
+                    FloatRule::Threshold(_) => 0.0,
+                }
+            })
+            .collect();
+    }
+
+    /// Draw the `FloatWorld` state to the frame buffer, as shades of gray
+    /// going from light (`0.0`) to dark (`1.0`).
+    pub fn draw(&self, frame: &mut [u8]) {
+        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+            let shade = (0xF8 as f32 - self.values[i] * (0xF8 - 0x1E) as f32) as u8;
+            pixel.copy_from_slice(&[shade, shade, shade, 0xFF]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniform_field_stays_uniform() {
+        let mut world = FloatWorld::new(6, 5, 1, FloatRule::Smooth);
+        for index in 0..30 {
+            world.set_value(index, 0.25);
+        }
+        world.paused = false;
+        world.update();
+
+        for index in 0..30 {
+            assert!((world.value(index).unwrap() - 0.25).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn threshold_rule_binarizes_the_field() {
+        let mut world = FloatWorld::new(5, 1, 1, FloatRule::Threshold(0.5));
+        for (index, &value) in [0.0, 0.9, 0.9, 0.3, 0.0].iter().enumerate() {
+            world.set_value(index, value);
+        }
+        world.paused = false;
+        world.update();
+
+        // Means of the 3x3 squares around each cell, rows wrapping onto
+        // themselves: 0.3, 0.6, 0.7, 0.4, 0.1
+        let values: Vec<f32> = (0..5).map(|index| world.value(index).unwrap()).collect();
+        assert_eq!(values, [0.0, 1.0, 1.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,86 @@
+//! apgcodes, the canonical names Catagolue gives to still lifes (`xs`),
+//! oscillators (`xp`) and spaceships (`xq`): a prefix followed by the
+//! population or period, `_`, and the cells in extended Wechsler format.
+//!
+//! The pattern is cut in strips of 5 rows separated by `z`. Each strip is a
+//! run of columns, a column being a base 32 digit (`0`-`9`, `a`-`v`) whose
+//! bit `n` is the cell of the `n`th row of the strip. `w` and `x` stand for 2
+//! and 3 empty columns, `y` followed by a base 36 digit `d` for `4 + d`.
+
+use super::{Pattern, PatternError};
+
+/// Number of rows encoded by a strip.
+const STRIP_HEIGHT: usize = 5;
+
+/// Parse an apgcode, errors being located on line 1 at the column of the
+/// offending character.
+pub fn parse(code: &str) -> Result<Pattern, PatternError> {
+    let code = code.trim();
+    let error = |column: usize, message: String| {
+        PatternError::parse_at(
+            1,
+            column,
+            format!("invalid apgcode `{}`: {}", code, message),
+        )
+    };
+
+    let mut parts = code.splitn(2, '_');
+    let (prefix, cells) = match (parts.next(), parts.next()) {
+        (Some(prefix), Some(cells)) => (prefix, cells),
+        _ => return Err(error(1, "expected `<prefix>_<cells>`".to_string())),
+    };
+    let number = ["xs", "xp", "xq"]
+        .iter()
+        .find_map(|kind| prefix.strip_prefix(kind))
+        .ok_or_else(|| {
+            PatternError::Unsupported(format!(
+                "apgcode prefix `{}`, expected xs, xp or xq",
+                prefix
+            ))
+        })?;
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return Err(error(1, format!("invalid number in prefix `{}`", prefix)));
+    }
+
+    let mut live = Vec::new();
+    let (mut x, mut strip) = (0, 0);
+    // Columns of the cells are 1-based and follow the prefix and `_`
+    let mut chars = cells
+        .chars()
+        .enumerate()
+        .map(|(i, c)| (prefix.len() + 2 + i, c));
+    while let Some((column, c)) = chars.next() {
+        match c {
+            'z' => {
+                strip += 1;
+                x = 0;
+            }
+            'w' => x += 2,
+            'x' => x += 3,
+            'y' => {
+                let count = chars
+                    .next()
+                    .and_then(|(_, c)| c.to_digit(36))
+                    .ok_or_else(|| error(column, "`y` must be followed by a digit".to_string()))?;
+                x += 4 + count as usize;
+            }
+            _ => {
+                let bits = c
+                    .to_digit(32)
+                    .filter(|_| !c.is_ascii_uppercase())
+                    .ok_or_else(|| error(column, format!("unexpected character `{}`", c)))?;
+                for row in 0..STRIP_HEIGHT {
+                    if bits & (1 << row) != 0 {
+                        live.push((x, strip * STRIP_HEIGHT + row));
+                    }
+                }
+                x += 1;
+            }
+        }
+    }
+
+    if live.is_empty() {
+        return Err(error(1, "no alive cell".to_string()));
+    }
+    Ok(Pattern::new(live))
+}
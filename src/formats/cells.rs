@@ -0,0 +1,41 @@
+//! Plaintext format: one line per row, `.` for dead cells and `O` for alive
+//! cells, lines starting with `!` being comments.
+
+use super::{Pattern, PatternError};
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut cells = Vec::new();
+    let rows = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('!'));
+
+    for (y, (line, row)) in rows.enumerate() {
+        for (x, c) in row.trim_end().chars().enumerate() {
+            match c {
+                'O' | '*' => cells.push((x, y)),
+                '.' => {}
+                _ => {
+                    return Err(PatternError::parse_at(
+                        line + 1,
+                        x + 1,
+                        format!("unexpected character `{}`", c),
+                    ))
+                }
+            }
+        }
+    }
+
+    Ok(Pattern::new(cells))
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut rows = vec![vec!['.'; pattern.width]; pattern.height];
+    for &(x, y) in &pattern.cells {
+        rows[y][x] = 'O';
+    }
+
+    rows.into_iter()
+        .map(|row| row.into_iter().collect::<String>() + "\n")
+        .collect()
+}
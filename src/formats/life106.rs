@@ -0,0 +1,70 @@
+//! Life 1.06 format: a `#Life 1.06` header followed by the `x y` coordinates
+//! of every alive cell, one per line.
+
+use super::{Pattern, PatternError};
+
+const HEADER: &str = "#Life 1.06";
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut lines = text.lines().enumerate();
+
+    match lines.next() {
+        Some((_, header)) if header.trim() == HEADER => {}
+        Some((_, header)) if header.trim() == "#Life 1.05" => {
+            return Err(PatternError::Unsupported(
+                "Life 1.05 pattern, only Life 1.06 is read".to_string(),
+            ))
+        }
+        _ => {
+            return Err(PatternError::parse(
+                1,
+                format!("expected `{}` header", HEADER),
+            ))
+        }
+    }
+
+    let mut coords = Vec::new();
+    for (line, content) in lines {
+        let content = content.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+
+        let values = content
+            .split_whitespace()
+            .map(str::parse::<isize>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PatternError::parse(line + 1, e.to_string()))?;
+
+        match values.as_slice() {
+            &[x, y] => coords.push((line + 1, x, y)),
+            _ => return Err(PatternError::parse(line + 1, "expected `x y` coordinates")),
+        }
+    }
+
+    let min_x = coords.iter().map(|&(_, x, _)| x).min().unwrap_or(0);
+    let min_y = coords.iter().map(|&(_, _, y)| y).min().unwrap_or(0);
+
+    let cells = coords
+        .into_iter()
+        .map(
+            |(line, x, y)| match (x.checked_sub(min_x), y.checked_sub(min_y)) {
+                (Some(x), Some(y)) => Ok((x as usize, y as usize)),
+                _ => Err(PatternError::parse(
+                    line,
+                    format!("cell `{} {}` too far from the others", x, y),
+                )),
+            },
+        )
+        .collect::<Result<_, _>>()?;
+    Ok(Pattern::new(cells))
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut text = format!("{}\n", HEADER);
+    for &(x, y) in &pattern.cells {
+        text.push_str(&format!("{} {}\n", x, y));
+    }
+
+    text
+}
@@ -0,0 +1,267 @@
+//! Readers and writers for the usual pattern file formats.
+
+use crate::rule::Rule;
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+pub mod apgcode;
+pub mod cells;
+pub mod life106;
+pub mod rle;
+
+/// Live cells of a pattern, as `(x, y)` coordinates relative to the top-left
+/// corner of its bounding box, in row-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<(usize, usize)>,
+    /// Rule the pattern is meant to run, for the formats recording it
+    pub rule: Option<Rule>,
+}
+
+impl Pattern {
+    pub fn new(mut cells: Vec<(usize, usize)>) -> Self {
+        cells.sort_by_key(|&(x, y)| (y, x));
+        cells.dedup();
+        let (width, height) = crate::patterns::size(&cells);
+
+        Self {
+            width,
+            height,
+            cells,
+            rule: None,
+        }
+    }
+}
+
+/// Error met while loading a pattern, whatever its format.
+#[derive(Debug)]
+pub enum PatternError {
+    Io(io::Error),
+    /// Malformed pattern, at a line and column (in characters, both 1-based)
+    Parse {
+        line: usize,
+        column: Option<usize>,
+        message: String,
+    },
+    /// Feature of the format that cannot be represented by two-state worlds
+    Unsupported(String),
+    /// Alive cells beyond the width and height declared by the pattern, or
+    /// those of the world it is loaded in
+    DimensionMismatch {
+        declared: (usize, usize),
+        actual: (usize, usize),
+    },
+}
+
+impl PatternError {
+    fn parse(line: usize, message: impl Into<String>) -> Self {
+        PatternError::Parse {
+            line,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    fn parse_at(line: usize, column: usize, message: impl Into<String>) -> Self {
+        PatternError::Parse {
+            line,
+            column: Some(column),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::Io(e) => write!(f, "{}", e),
+            PatternError::Parse {
+                line,
+                column: Some(column),
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            PatternError::Parse {
+                line,
+                column: None,
+                message,
+            } => write!(f, "line {}: {}", line, message),
+            PatternError::Unsupported(feature) => write!(f, "unsupported {}", feature),
+            PatternError::DimensionMismatch { declared, actual } => write!(
+                f,
+                "cells span {}x{}, more than the declared {}x{}",
+                actual.0, actual.1, declared.0, declared.1
+            ),
+        }
+    }
+}
+
+impl error::Error for PatternError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PatternError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PatternError {
+    fn from(e: io::Error) -> Self {
+        PatternError::Io(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Rle,
+    Cells,
+    Life106,
+}
+
+impl Format {
+    /// Guess the format of a pattern file from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "rle" => Some(Format::Rle),
+            "cells" => Some(Format::Cells),
+            "lif" | "life" => Some(Format::Life106),
+            _ => None,
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Pattern, PatternError> {
+        match self {
+            Format::Rle => rle::parse(text),
+            Format::Cells => cells::parse(text),
+            Format::Life106 => life106::parse(text),
+        }
+    }
+
+    pub fn write(self, pattern: &Pattern) -> String {
+        match self {
+            Format::Rle => rle::write(pattern),
+            Format::Cells => cells::write(pattern),
+            Format::Life106 => life106::write(pattern),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rle" => Ok(Format::Rle),
+            "cells" => Ok(Format::Cells),
+            "life106" | "lif" | "life" => Ok(Format::Life106),
+            _ => Err(format!(
+                "unknown format `{}`, expected one of rle, cells, life106",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error_location(result: Result<Pattern, PatternError>) -> (usize, Option<usize>) {
+        match result {
+            Err(PatternError::Parse { line, column, .. }) => (line, column),
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error, got a pattern"),
+        }
+    }
+
+    #[test]
+    fn patterns_convert_between_every_format() {
+        let glider = Pattern::new(vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let formats = [Format::Rle, Format::Cells, Format::Life106];
+
+        for &from in &formats {
+            let parsed = from.parse(&from.write(&glider)).unwrap();
+            for &to in &formats {
+                let converted = to.parse(&to.write(&parsed)).unwrap();
+                assert_eq!(converted.cells, glider.cells, "{:?} to {:?}", from, to);
+                assert_eq!((converted.width, converted.height), (3, 3));
+            }
+        }
+        assert_eq!(cells::write(&glider), ".O.\n..O\nOOO\n");
+        assert_eq!(
+            Format::from_path(Path::new("glider.LIF")),
+            Some(Format::Life106)
+        );
+    }
+
+    #[test]
+    fn malformed_rle_is_located() {
+        let rle = "#C glider\nx = 3, y = 3\nbo$2bo$3?!\n";
+        assert_eq!(parse_error_location(rle::parse(rle)), (3, Some(9)));
+    }
+
+    #[test]
+    fn rle_runs_past_the_declared_width_are_rejected() {
+        let rle = "x = 3, y = 1\n2o2o!\n";
+        assert_eq!(parse_error_location(rle::parse(rle)), (2, Some(4)));
+
+        let rle = format!("x = 3, y = 1\n{}o!\n", usize::MAX);
+        assert!(matches!(rle::parse(&rle), Err(PatternError::Parse { .. })));
+    }
+
+    #[test]
+    fn glider_apgcode_decodes_to_its_five_cells() {
+        let glider = apgcode::parse("xq4_153").unwrap();
+        let mut cells = glider.cells.clone();
+        cells.sort_unstable();
+
+        assert_eq!(cells, [(0, 0), (1, 0), (1, 2), (2, 0), (2, 1)]);
+        assert_eq!((glider.width, glider.height), (3, 3));
+        assert!(matches!(
+            apgcode::parse("xq4_1!3"),
+            Err(PatternError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn written_rle_reads_back_the_same_pattern_and_rule() {
+        // Long enough rows to be wrapped, with gaps and empty rows
+        let cells: Vec<(usize, usize)> = (0..120)
+            .filter(|x| x % 3 != 1)
+            .map(|x| (x, (x % 5) * 2))
+            .collect();
+        let pattern = Pattern {
+            rule: Some(Rule::parse("B36/S23").unwrap()),
+            ..Pattern::new(cells)
+        };
+
+        let text = rle::write(&pattern);
+        assert!(
+            text.starts_with("x = 120, y = 9, rule = B36/S23\n"),
+            "{}",
+            text
+        );
+        assert!(text.lines().all(|line| line.len() <= 70));
+        assert_eq!(rle::parse(&text).unwrap(), pattern);
+
+        let larger_than_life = Pattern {
+            rule: Some(Rule::parse("B3,12-14/S2,9").unwrap()),
+            ..Pattern::new(vec![(0, 0)])
+        };
+        let text = rle::write(&larger_than_life);
+        assert!(text.starts_with("x = 1, y = 1, rule = B3,12-14/S2,9\n"));
+        assert_eq!(rle::parse(&text).unwrap(), larger_than_life);
+    }
+
+    #[test]
+    fn life106_coordinates_too_far_apart_are_rejected() {
+        let text = format!("#Life 1.06\n{} 0\n{} 0\n", isize::MIN, isize::MAX);
+        assert_eq!(parse_error_location(life106::parse(&text)), (3, None));
+    }
+}
@@ -0,0 +1,247 @@
+//! Run Length Encoded format: a `x = .., y = .., rule = ..` header (the rule
+//! being optional) followed by runs of dead (`b`) and alive (`o`) cells, rows
+//! being ended by `$` and the pattern by `!`.
+
+use super::{Pattern, PatternError};
+use crate::rule::Rule;
+
+/// Maximum length of the lines written, as recommended by the format.
+const LINE_LENGTH: usize = 70;
+
+/// Width, height and rule declared by a header.
+type Header = (usize, usize, Option<Rule>);
+
+fn parse_header(line: usize, header: &str) -> Result<Header, PatternError> {
+    let mut width = None;
+    let mut height = None;
+    let mut rule = None;
+
+    // The rule comes last, and lists its counts past 8 separated by commas
+    let (header, rule_field) = match header.find("rule") {
+        Some(at) => (
+            header[..at].trim_end().trim_end_matches(','),
+            Some(&header[at..]),
+        ),
+        None => (header, None),
+    };
+    for field in header.split(',').chain(rule_field) {
+        let mut parts = field.splitn(2, '=').map(str::trim);
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key, value),
+            _ => {
+                return Err(PatternError::parse(
+                    line,
+                    format!("invalid header field `{}`", field),
+                ))
+            }
+        };
+
+        let dimension = match key {
+            "x" => &mut width,
+            "y" => &mut height,
+            "rule" => {
+                rule =
+                    Some(Rule::parse(value).map_err(|e| PatternError::parse(line, e.to_string()))?);
+                continue;
+            }
+            _ => continue,
+        };
+        *dimension = Some(value.parse::<usize>().map_err(|e| {
+            PatternError::parse(line, format!("invalid `{}` value `{}`: {}", key, value, e))
+        })?);
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule)),
+        _ => Err(PatternError::parse(
+            line,
+            "header must define both `x` and `y`",
+        )),
+    }
+}
+
+pub fn parse(text: &str) -> Result<Pattern, PatternError> {
+    let mut header = None;
+    let mut cells = Vec::new();
+    let (mut x, mut y): (usize, usize) = (0, 0);
+    let mut run: Option<usize> = None;
+    let mut terminated = false;
+    let mut last_line = 0;
+
+    'lines: for (line, raw) in text.lines().enumerate() {
+        let line = line + 1;
+        let content = raw.trim();
+        last_line = line;
+
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+
+        let (width, height) = match header {
+            Some((width, height, _)) => (width, height),
+            None => {
+                header = Some(parse_header(line, content)?);
+                continue;
+            }
+        };
+
+        for (column, c) in raw.chars().enumerate() {
+            let column = column + 1;
+            match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    run = run
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|run| run.checked_add(digit));
+                    if run.is_none() {
+                        return Err(PatternError::parse_at(
+                            line,
+                            column,
+                            "run count is too large",
+                        ));
+                    }
+                }
+                'b' | 'o' => {
+                    let count = run.take().unwrap_or(1);
+                    let end = match x.checked_add(count) {
+                        Some(end) if end <= width => end,
+                        _ => {
+                            return Err(PatternError::parse_at(
+                                line,
+                                column,
+                                format!("run of {} cells past the declared width {}", count, width),
+                            ))
+                        }
+                    };
+                    if c == 'o' {
+                        if y >= height {
+                            return Err(PatternError::parse_at(
+                                line,
+                                column,
+                                format!("alive cells past the declared height {}", height),
+                            ));
+                        }
+                        cells.extend((x..end).map(|x| (x, y)));
+                    }
+                    x = end;
+                }
+                '$' => {
+                    y = match y.checked_add(run.take().unwrap_or(1)) {
+                        Some(y) => y,
+                        None => {
+                            return Err(PatternError::parse_at(
+                                line,
+                                column,
+                                "row count is too large",
+                            ))
+                        }
+                    };
+                    x = 0;
+                }
+                '!' => {
+                    terminated = true;
+                    break 'lines;
+                }
+                c if c.is_whitespace() => {}
+                'A'..='X' => {
+                    return Err(PatternError::Unsupported(format!(
+                        "multi-state cell `{}` at line {}, column {}",
+                        c, line, column
+                    )))
+                }
+                _ => {
+                    return Err(PatternError::parse_at(
+                        line,
+                        column,
+                        format!("unexpected character `{}`", c),
+                    ))
+                }
+            }
+        }
+    }
+
+    let (width, height, rule) = match header {
+        Some(header) => header,
+        None => {
+            return Err(PatternError::parse(
+                last_line,
+                "missing `x = .., y = ..` header",
+            ))
+        }
+    };
+    if !terminated {
+        return Err(PatternError::parse(last_line, "missing `!` terminator"));
+    }
+
+    let pattern = Pattern::new(cells);
+    if pattern.width > width || pattern.height > height {
+        return Err(PatternError::DimensionMismatch {
+            declared: (width, height),
+            actual: (pattern.width, pattern.height),
+        });
+    }
+    Ok(Pattern {
+        width,
+        height,
+        rule,
+        ..pattern
+    })
+}
+
+fn push_run(tokens: &mut Vec<String>, count: usize, tag: char) {
+    match count {
+        0 => {}
+        1 => tokens.push(tag.to_string()),
+        _ => tokens.push(format!("{}{}", count, tag)),
+    }
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut rows = vec![Vec::new(); pattern.height];
+    for &(x, y) in &pattern.cells {
+        rows[y].push(x);
+    }
+
+    let mut tokens = Vec::new();
+    let mut pending_rows = 0;
+    for row in rows {
+        if !row.is_empty() {
+            push_run(&mut tokens, pending_rows, '$');
+            pending_rows = 0;
+
+            let mut x = 0;
+            let mut alive = 0;
+            for cell in row {
+                if cell != x + alive {
+                    push_run(&mut tokens, alive, 'o');
+                    push_run(&mut tokens, cell - x - alive, 'b');
+                    x = cell;
+                    alive = 0;
+                }
+                alive += 1;
+            }
+            push_run(&mut tokens, alive, 'o');
+        }
+        pending_rows += 1;
+    }
+    tokens.push("!".to_string());
+
+    let mut text = format!("x = {}, y = {}", pattern.width, pattern.height);
+    if let Some(rule) = &pattern.rule {
+        text.push_str(&format!(", rule = {}", rule));
+    }
+    text.push('\n');
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length + token.len() > LINE_LENGTH {
+            text.push('\n');
+            line_length = 0;
+        }
+        line_length += token.len();
+        text.push_str(&token);
+    }
+    text.push('\n');
+
+    text
+}
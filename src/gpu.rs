@@ -0,0 +1,251 @@
+//! Generations of large Life-like worlds computed by a compute shader, built
+//! with the `gpu` feature. The cells are uploaded to the GPU, stepped by
+//! `shaders/life.comp` and read back each generation.
+
+use crate::automata::{Automaton, Boundary, Neighbourhood, State, World};
+use std::io::Cursor;
+
+/// Cells stepped by each workgroup along both axes, as declared by the shader.
+const WORKGROUP_SIZE: usize = 8;
+
+/// Connection to a GPU and the pipeline running the shader on it.
+pub struct GpuStepper {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl GpuStepper {
+    /// Connect to a GPU, `None` when there is none.
+    pub fn new() -> Option<Self> {
+        let adapter = pollster::block_on(wgpu::Adapter::request(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+            },
+            wgpu::BackendBit::PRIMARY,
+        ))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            extensions: wgpu::Extensions {
+                anisotropic_filtering: false,
+            },
+            limits: wgpu::Limits::default(),
+        }));
+
+        let spirv = wgpu::read_spirv(Cursor::new(&include_bytes!("../shaders/life.spv")[..]))
+            .expect("invalid SPIR-V for the Life shader");
+        let module = device.create_shader_module(&spirv);
+
+        let storage = |binding, readonly| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::COMPUTE,
+            ty: wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                readonly,
+            },
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            bindings: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                },
+                storage(1, true),
+                storage(2, false),
+            ],
+            label: Some("life"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: &[&layout],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            layout: &pipeline_layout,
+            compute_stage: wgpu::ProgrammableStageDescriptor {
+                module: &module,
+                entry_point: "main",
+            },
+        });
+
+        Some(Self {
+            device,
+            queue,
+            layout,
+            pipeline,
+        })
+    }
+
+    /// Whether the shader can step `world`: a Life-like world with the Moore
+    /// neighbourhood of radius 1, wrapping around and at least 3 cells wide
+    /// and tall gutter included, whose cells are all dead or alive, neither
+    /// frozen nor masked out. Region rules, a maximum population and payloads
+    /// are not supported either.
+    pub fn supports(world: &World) -> bool {
+        let width = world.width() + 2 * world.gutter();
+        let height = world.height() + 2 * world.gutter();
+        matches!(world.automaton, Automaton::LifeLike)
+            && world.neighbourhood() == Neighbourhood::Moore
+            && world.radius() == 1
+            && world.boundary() == Boundary::Toroidal
+            && width >= 3
+            && height >= 3
+            && world.region_rules.is_empty()
+            && world.max_population.is_none()
+            && world.payload_rule.is_none()
+            && (0..world.cell_count()).all(|i| {
+                matches!(world.cell_state(i), Some(State::ALIVE) | Some(State::DEAD))
+                    && !world.is_frozen(i)
+                    && world.is_active(i)
+            })
+    }
+
+    /// Compute the next generation of `world`, even when it is paused: on the
+    /// GPU when it `supports` the world, on the CPU otherwise.
+    pub fn step(&self, world: &mut World) {
+        if !Self::supports(world) {
+            world.step();
+            return;
+        }
+
+        let next = self.next_alive(world);
+        let changes: Vec<(usize, State)> = next
+            .into_iter()
+            .enumerate()
+            .filter(|&(i, alive)| (world.cell_state(i) == Some(State::ALIVE)) != alive)
+            .map(|(i, alive)| (i, if alive { State::ALIVE } else { State::DEAD }))
+            .collect();
+        world.apply_changes(&changes);
+    }
+
+    /// Whether each cell of `world` is alive at the next generation.
+    fn next_alive(&self, world: &World) -> Vec<bool> {
+        let width = world.width() + 2 * world.gutter();
+        let height = world.height() + 2 * world.gutter();
+        let size = (world.cell_count() * 4) as wgpu::BufferAddress;
+
+        let mask = |alive| {
+            (0..=8)
+                .filter(|&n| world.rule.next_alive(alive, n))
+                .fold(0u32, |mask, n| mask | 1 << n)
+        };
+        let params: Vec<u8> = [width as u32, height as u32, mask(false), mask(true)]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes().to_vec())
+            .collect();
+        let cells: Vec<u8> = (0..world.cell_count())
+            .flat_map(|i| {
+                ((world.cell_state(i) == Some(State::ALIVE)) as u32)
+                    .to_ne_bytes()
+                    .to_vec()
+            })
+            .collect();
+
+        let params = self
+            .device
+            .create_buffer_with_data(&params, wgpu::BufferUsage::UNIFORM);
+        let current = self
+            .device
+            .create_buffer_with_data(&cells, wgpu::BufferUsage::STORAGE);
+        let next = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("next"),
+            size,
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_SRC,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let buffer = |binding, buffer| wgpu::Binding {
+            binding,
+            resource: wgpu::BindingResource::Buffer {
+                buffer,
+                range: 0..size,
+            },
+        };
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &params,
+                        range: 0..16,
+                    },
+                },
+                buffer(1, &current),
+                buffer(2, &next),
+            ],
+            label: Some("life"),
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass();
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let groups = |cells: usize| cells.div_ceil(WORKGROUP_SIZE) as u32;
+            pass.dispatch(groups(width), groups(height), 1);
+        }
+        encoder.copy_buffer_to_buffer(&next, 0, &readback, 0, size);
+        self.queue.submit(&[encoder.finish()]);
+
+        let mapping = readback.map_read(0, size);
+        self.device.poll(wgpu::Maintain::Wait);
+        let mapping = pollster::block_on(mapping).expect("failed to read the next generation back");
+        mapping
+            .as_slice()
+            .chunks_exact(4)
+            .map(|bytes| bytes.iter().any(|&byte| byte != 0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns;
+    use crate::random::Rng;
+    use crate::rule::Rule;
+
+    #[test]
+    fn gpu_generations_match_the_cpu() {
+        let gpu = match GpuStepper::new() {
+            Some(gpu) => gpu,
+            // Nothing to compare against without a GPU
+            None => return,
+        };
+
+        let mut cpu_world = World::new(61, 37, 1, Rule::parse("B36/S23").unwrap());
+        let soup = patterns::soup(61, 37, 0.4, &mut Rng::new(7));
+        cpu_world.stamp(&soup, (0, 0), State::ALIVE);
+        let mut gpu_world = cpu_world.clone();
+        assert!(GpuStepper::supports(&gpu_world));
+
+        for _ in 0..20 {
+            cpu_world.step();
+            gpu.step(&mut gpu_world);
+            assert_eq!(gpu_world.live_cells(), cpu_world.live_cells());
+            assert_eq!(gpu_world.generation(), cpu_world.generation());
+        }
+    }
+
+    #[test]
+    fn unsupported_worlds_are_stepped_on_the_cpu() {
+        let mut world = World::new(8, 8, 0, Rule::default());
+        world.stamp(&[(1, 0), (1, 1), (1, 2)], (2, 2), State::ALIVE);
+        world.set_boundary(Boundary::Fixed);
+        assert!(!GpuStepper::supports(&world));
+
+        let mut expected = world.clone();
+        expected.step();
+        if let Some(gpu) = GpuStepper::new() {
+            gpu.step(&mut world);
+            assert_eq!(world.live_cells(), expected.live_cells());
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Recent generations and edits of a `World`, to scrub back and forth through
+//! them and undo or redo the edits.
+
+use crate::automata::{Snapshot, World};
+use std::collections::VecDeque;
+
+/// Ring buffer of the last `capacity` recorded snapshots, with a position
+/// that can be moved through them. Recording a snapshot while positioned
+/// before the most recent one drops the snapshots after the position, as an
+/// undo history would.
+pub struct History {
+    capacity: usize,
+    snapshots: VecDeque<Snapshot>,
+    position: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            snapshots: VecDeque::with_capacity(capacity),
+            position: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.position = 0;
+    }
+
+    /// Record the current state of `world` after the current position.
+    pub fn record(&mut self, world: &World) {
+        self.push(world.snapshot());
+    }
+
+    /// Record `snapshot` after the current position.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if !self.snapshots.is_empty() {
+            self.snapshots.truncate(self.position + 1);
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(snapshot);
+        self.position = self.snapshots.len() - 1;
+    }
+
+    /// Snapshot at the current position.
+    pub fn current(&self) -> Option<&Snapshot> {
+        self.snapshots.get(self.position)
+    }
+
+    /// Move one snapshot back, if any.
+    pub fn back(&mut self) -> Option<&Snapshot> {
+        if self.position == 0 {
+            return None;
+        }
+
+        self.position -= 1;
+        self.current()
+    }
+
+    /// Move one snapshot forward, if any.
+    pub fn forward(&mut self) -> Option<&Snapshot> {
+        if self.position + 1 >= self.snapshots.len() {
+            return None;
+        }
+
+        self.position += 1;
+        self.current()
+    }
+
+    /// Move to the snapshot of `generation`, if it is still recorded.
+    pub fn seek(&mut self, generation: u64) -> Option<&Snapshot> {
+        self.position = self
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.generation() == generation)?;
+        self.current()
+    }
+
+    /// Generations of the oldest and newest snapshots recorded.
+    pub fn range(&self) -> Option<(u64, u64)> {
+        Some((
+            self.snapshots.front()?.generation(),
+            self.snapshots.back()?.generation(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::State;
+    use crate::patterns;
+    use crate::rule::Rule;
+
+    #[test]
+    fn seeking_restores_the_recorded_state() {
+        let mut world = World::new(8, 8, 0, Rule::default());
+        world.stamp(&patterns::glider(), (1, 1), State::ALIVE);
+        let mut history = History::new(16);
+        history.record(&world);
+        for _ in 0..5 {
+            world.step();
+            history.record(&world);
+        }
+        let cells = world.live_cells();
+        for _ in 0..5 {
+            world.step();
+            history.record(&world);
+        }
+        assert_eq!(history.range(), Some((0, 10)));
+
+        world.restore(history.seek(5).unwrap());
+        assert_eq!(world.generation(), 5);
+        assert_eq!(world.live_cells(), cells);
+        assert_eq!(history.seek(11), None);
+    }
+
+    #[test]
+    fn recording_after_going_back_drops_the_later_snapshots() {
+        let mut world = World::new(8, 8, 0, Rule::default());
+        world.stamp(&patterns::blinker(), (2, 2), State::ALIVE);
+        let mut history = History::new(16);
+        history.record(&world);
+        for _ in 0..3 {
+            world.step();
+            history.record(&world);
+        }
+
+        world.restore(history.seek(1).unwrap());
+        world.step();
+        history.record(&world);
+        assert_eq!(history.range(), Some((0, 2)));
+        assert_eq!(history.forward(), None);
+    }
+}
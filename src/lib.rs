@@ -0,0 +1,16 @@
+#![deny(clippy::all)]
+#![forbid(unsafe_code)]
+
+pub mod automata;
+pub mod continuous;
+pub mod formats;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod history;
+pub mod montage;
+pub mod patterns;
+pub mod random;
+pub mod recorder;
+pub mod replay;
+pub mod rule;
+pub mod theme;
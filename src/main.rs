@@ -1,20 +1,33 @@
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "gpu")]
+use cellular_automata::gpu;
+use cellular_automata::{
+    automata, formats, history, patterns, random, recorder, replay, rule, theme,
+};
 use clap::Clap;
-use log::error;
-use pixels::{wgpu::Surface, Error, Pixels, SurfaceTexture};
-use std::path::Path;
+use log::{error, warn};
+use pixels::{wgpu::Surface, Pixels, SurfaceTexture};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use winit::dpi::{LogicalSize, PhysicalPosition};
+use winit::error::OsError;
 use winit::event::{Event, VirtualKeyCode};
-use winit::event_loop::{ControlFlow, EventLoop};
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
 use winit::window::{Icon, WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 extern crate image;
 
-mod automata;
-
-fn mouse_index(input: &mut WinitInputHelper, pixels: &mut Pixels, width: usize) -> Option<usize> {
+/// Coordinates of the hovered cell within the visible area.
+fn mouse_coords(input: &mut WinitInputHelper, pixels: &mut Pixels) -> Option<(usize, usize)> {
     input
         .mouse()
         .map(|(x, y)| PhysicalPosition::new(x, y).into())
@@ -23,7 +36,14 @@ fn mouse_index(input: &mut WinitInputHelper, pixels: &mut Pixels, width: usize)
                 .window_pos_to_pixel((x, y))
                 .unwrap_or_else(|pos| pixels.clamp_pixel_pos(pos))
         })
-        .map(|(x, y)| automata::utils::coords_to_index(x, y, width))
+}
+
+fn mouse_index(
+    input: &mut WinitInputHelper,
+    pixels: &mut Pixels,
+    world: &automata::World,
+) -> Option<usize> {
+    mouse_coords(input, pixels).map(|(x, y)| world.index(x, y))
 }
 
 fn load_icon(path: &Path) -> Option<Icon> {
@@ -45,29 +65,1031 @@ fn load_icon(path: &Path) -> Option<Icon> {
     about = "This program implements a basic cellular automata following Conway's Game of Life rules"
 )]
 struct Opts {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    // Options of `run`, which is run when no subcommand is given
+    #[clap(flatten)]
+    run: RunOpts,
+}
+
+#[derive(Clap)]
+enum Command {
+    /// Run the simulation in a window (default)
+    Run(RunOpts),
+    /// Run the simulation without a window and print the final population
+    Headless(HeadlessOpts),
+    /// Run the simulation without a window and record its generations as a GIF
+    Record(RecordOpts),
+    /// Convert a pattern file between the RLE, plaintext and Life 1.06 formats
+    Convert(ConvertOpts),
+    /// Run many random soups without a window and report the most interesting ones
+    SoupSearch(SoupSearchOpts),
+}
+
+#[derive(Clap)]
+struct WorldOpts {
+    #[clap(short, long, default_value = "150")]
+    width: usize,
+
+    #[clap(short, long, default_value = "100")]
+    height: usize,
+
+    /// Name of a built-in pattern to stamp at the center of the world
+    #[clap(long)]
+    start_pattern: Option<String>,
+
+    /// Pattern file to stamp at the center of the world, `-` to read it from stdin
+    #[clap(short, long)]
+    pattern: Option<PathBuf>,
+
+    /// Format of the pattern file (rle, cells or life106), guessed from its extension if omitted
+    #[clap(long)]
+    format: Option<formats::Format>,
+
+    /// apgcode of a pattern to stamp at the center of the world, e.g. xq4_153 for a glider
+    #[clap(long, conflicts_with = "pattern")]
+    apgcode: Option<String>,
+
+    /// Seed of a random soup to stamp at the center of the world, as reported by soup-search
+    #[clap(long)]
+    soup_seed: Option<u64>,
+
+    /// Width and height of the random soup
+    #[clap(long, default_value = "16")]
+    soup_size: usize,
+
+    /// Number of hidden dead cells simulated around each edge of the world
+    #[clap(long, default_value = "0")]
+    gutter: usize,
+
+    /// Size the world to the pattern file (as declared by its header, if any) or apgcode instead of --width and --height
+    #[clap(long)]
+    fit_pattern: bool,
+
+    /// Number of dead cells added around the pattern on each side by --fit-pattern
+    #[clap(long, default_value = "0")]
+    fit_margin: usize,
+
+    /// Restrict the world to the disc inscribed in it, the cells outside never coming to life
+    #[clap(long)]
+    disc: bool,
+
+    /// Image to load the world from, sized to the image: dark pixels are alive cells
+    #[clap(long)]
+    image: Option<PathBuf>,
+
+    /// Compute generations in `parallel`, `sequential`ly, or pick from the world size (`auto`)
+    #[clap(long, default_value = "auto")]
+    parallelism: automata::Parallelism,
+
+    /// Life-like rule in B/S notation, e.g. B36/S23 for HighLife [default: the rule of the
+    /// pattern file if any, B3/S23 otherwise]
+    #[clap(long)]
+    rule: Option<rule::Rule>,
+
+    /// Count each cell among its own neighbours (inner-totalistic rule)
+    #[clap(long)]
+    include_center: bool,
+
+    /// Cellwise `life-like` rule, `critters` or `billiard-ball` Margolus block rule, `wireworld`, or `brians-brain`
+    #[clap(long, default_value = "life-like")]
+    automaton: automata::Automaton,
+
+    /// File of `from state:counts -> to` transitions to run instead of --rule and --automaton
+    #[clap(long)]
+    rule_table: Option<PathBuf>,
+
+    /// Cells counted as neighbours: `moore` (8 cells) or `von-neumann` (4 orthogonal cells)
+    #[clap(long, default_value = "moore")]
+    neighbourhood: automata::Neighbourhood,
+
+    /// Edges of the world: `toroidal` (wrapping around), `fixed` (dead walls) or `reflective` (mirrors)
+    #[clap(long, default_value = "toroidal")]
+    boundary: automata::Boundary,
+
+    /// Distance up to which cells count as neighbours, above 1 for Larger than Life rules such as
+    /// B34-45/S34-58
+    #[clap(long, default_value = "1")]
+    radius: usize,
+
+    /// Carrying capacity: births that would take the population over it don't happen
+    #[clap(long)]
+    max_population: Option<usize>,
+}
+
+impl WorldOpts {
+    fn build(&self) -> Result<automata::World, String> {
+        if self.width == 0 || self.height == 0 {
+            return Err("--width and --height must be at least 1".to_string());
+        }
+
+        let pattern = match (&self.pattern, &self.apgcode) {
+            (Some(path), _) => Some(load_pattern(path, self.format)?),
+            (None, Some(code)) => Some(formats::apgcode::parse(code).map_err(|e| e.to_string())?),
+            (None, None) => None,
+        };
+
+        let rule = rule::Rule {
+            include_center: self.include_center,
+            ..self
+                .rule
+                .or_else(|| pattern.as_ref().and_then(|pattern| pattern.rule))
+                .unwrap_or_default()
+        };
+
+        let mut world = match (&self.image, &pattern) {
+            (Some(path), _) => {
+                let mut world = automata::World::from_png(path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                world.rule = rule;
+                world
+            }
+            (None, Some(pattern)) if self.fit_pattern => automata::World::new(
+                pattern.width + 2 * self.fit_margin,
+                pattern.height + 2 * self.fit_margin,
+                self.gutter,
+                rule,
+            ),
+            (None, None) if self.fit_pattern => {
+                return Err("--fit-pattern requires a --pattern file or an --apgcode".to_string())
+            }
+            (None, _) => automata::World::new(self.width, self.height, self.gutter, rule),
+        };
+        world.parallelism = self.parallelism;
+        world.max_population = self.max_population;
+        world.set_neighbourhood(self.neighbourhood);
+        world.set_radius(self.radius);
+        world.set_boundary(self.boundary);
+        world.automaton = match &self.rule_table {
+            Some(path) => automata::Automaton::Table(load_rule_table(path)?),
+            None => self.automaton.clone(),
+        };
+
+        if self.disc {
+            world.set_active_mask(Some(world.disc_mask()));
+        }
+
+        if let Some(name) = &self.start_pattern {
+            let pattern = patterns::by_name(name).ok_or_else(|| {
+                format!(
+                    "Unknown pattern `{}`, available patterns: {}",
+                    name,
+                    patterns::names().join(", ")
+                )
+            })?;
+            world.stamp_centered(&pattern, automata::State::ALIVE);
+        }
+
+        if let Some(pattern) = &pattern {
+            world.stamp_centered(&pattern.cells, automata::State::ALIVE);
+        }
+
+        if let Some(seed) = self.soup_seed {
+            let soup = random_soup(self.soup_size, seed);
+            world.stamp_centered(&soup, automata::State::ALIVE);
+        }
+
+        Ok(world)
+    }
+}
+
+/// Probability for a cell of a random soup to be alive.
+const SOUP_DENSITY: f64 = 0.5;
+
+fn random_soup(size: usize, seed: u64) -> Vec<(usize, usize)> {
+    patterns::soup(size, size, SOUP_DENSITY, &mut random::Rng::new(seed))
+}
+
+#[derive(Clap)]
+struct RunOpts {
+    #[clap(flatten)]
+    world: WorldOpts,
+
+    /// Start running and pause once this generation is reached
+    #[clap(long)]
+    run_for: Option<u64>,
+
+    /// Pause once the world settles into a still life or a period 2 oscillator
+    #[clap(long)]
+    pause_when_stable: bool,
+
+    /// Pause once the population has stayed the same for --stasis-generations
+    #[clap(long)]
+    pause_on_stasis: bool,
+
+    /// Generations the population stays the same for before --pause-on-stasis pauses
+    #[clap(long, default_value = "10")]
+    stasis_generations: u64,
+
+    /// Initial size of a cell on screen, in logical pixels
+    #[clap(long, default_value = "4")]
+    cell_pixels: usize,
+
+    /// Palette file of `state = #RRGGBB[AA]` lines setting the colors of the cells
+    #[clap(long)]
+    palette: Option<PathBuf>,
+
+    /// Draw dead cells fully transparent in screenshots and GIFs, to lay them over a background
+    #[clap(long)]
+    transparent_dead: bool,
+
+    /// File to write the alive cells of every generation to, as JSON lines
+    #[clap(long)]
+    export_jsonl: Option<PathBuf>,
+
+    /// File to record the session to when the window is closed, to replay it with --play-replay
+    #[clap(long)]
+    record_replay: Option<PathBuf>,
+
+    /// Session recorded with --record-replay to replay instead of building a world
+    #[clap(long)]
+    play_replay: Option<PathBuf>,
+
+    /// Time each generation is shown for in the GIFs recorded with `C`, in milliseconds
+    #[clap(long, default_value = "100")]
+    gif_delay: u64,
+
+    /// Generations the trails toggled with `G` take to fade, up to 64
+    #[clap(long, default_value = "8")]
+    trail: u8,
+
+    /// Compute the generations on the GPU, for the worlds it supports (needs the `gpu` feature)
+    #[clap(long)]
+    gpu: bool,
+}
+
+#[derive(Clap)]
+struct HeadlessOpts {
+    #[clap(flatten)]
+    world: WorldOpts,
+
+    /// Number of generations to compute
+    #[clap(short = "n", long, default_value = "100")]
+    generations: u64,
+
+    /// File to write the alive cells of every generation to, as JSON lines
+    #[clap(long)]
+    export_jsonl: Option<PathBuf>,
+
+    /// Session recorded with --record-replay to replay to its end instead of computing -n generations
+    #[clap(long)]
+    play_replay: Option<PathBuf>,
+
+    /// Compute the generations on the GPU, for the worlds it supports (needs the `gpu` feature)
+    #[clap(long)]
+    gpu: bool,
+}
+
+#[derive(Clap)]
+struct RecordOpts {
+    #[clap(flatten)]
+    world: WorldOpts,
+
+    /// Number of generations to record after the initial one
+    #[clap(short = "n", long, default_value = "100")]
+    generations: u64,
+
+    /// GIF file to write the generations to
+    #[clap(short, long, default_value = "run.gif")]
+    output: PathBuf,
+
+    /// Time each generation is shown for in the GIF, in milliseconds
+    #[clap(long, default_value = "100")]
+    gif_delay: u64,
+
+    /// Palette file of `state = #RRGGBB[AA]` lines setting the colors of the cells
+    #[clap(long)]
+    palette: Option<PathBuf>,
+
+    /// Draw dead cells fully transparent, to lay the patterns over a background
+    #[clap(long)]
+    transparent_dead: bool,
+}
+
+#[derive(Clap)]
+struct ConvertOpts {
+    /// Pattern file to read, `-` to read it from stdin
+    #[clap(long)]
+    from: PathBuf,
+
+    /// Format of the pattern file to read (rle, cells or life106), guessed from its extension if omitted
+    #[clap(long)]
+    format: Option<formats::Format>,
+
+    /// Pattern file to write, its format being guessed from its extension
+    #[clap(long)]
+    to: PathBuf,
+}
+
+#[derive(Clap)]
+struct SoupSearchOpts {
     #[clap(short, long, default_value = "150")]
     width: usize,
 
     #[clap(short, long, default_value = "100")]
     height: usize,
+
+    /// Number of soups to run
+    #[clap(long, default_value = "100")]
+    count: u64,
+
+    /// Maximum number of generations to run each soup for
+    #[clap(long, default_value = "1000")]
+    steps: u64,
+
+    /// Seed of the first soup, the next ones using the following seeds
+    #[clap(long, default_value = "0")]
+    seed: u64,
+
+    /// Width and height of the random soups
+    #[clap(long, default_value = "16")]
+    soup_size: usize,
+
+    /// Sort the results by final `population` or by `lifespan`
+    #[clap(long, default_value = "population")]
+    by: SoupMetric,
+
+    /// Number of results to print
+    #[clap(long, default_value = "10")]
+    top: usize,
+}
+
+enum SoupMetric {
+    Population,
+    Lifespan,
+}
+
+impl std::str::FromStr for SoupMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "population" => Ok(SoupMetric::Population),
+            "lifespan" => Ok(SoupMetric::Lifespan),
+            _ => Err(format!(
+                "unknown metric `{}`, expected population or lifespan",
+                s
+            )),
+        }
+    }
+}
+
+/// Ways in which opening the window of the simulation can fail.
+#[derive(Debug)]
+enum RunError {
+    Window(OsError),
+    Pixels(pixels::Error),
 }
 
-fn main() -> Result<(), Error> {
+impl Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RunError::Window(e) => write!(f, "cannot create the window: {}", e),
+            RunError::Pixels(e) => write!(f, "cannot create the pixel buffer: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+impl From<OsError> for RunError {
+    fn from(e: OsError) -> Self {
+        RunError::Window(e)
+    }
+}
+
+impl From<pixels::Error> for RunError {
+    fn from(e: pixels::Error) -> Self {
+        RunError::Pixels(e)
+    }
+}
+
+fn main() {
     env_logger::init();
 
-    let Opts { width, height } = Opts::parse();
+    let opts = Opts::parse();
+    match opts.command {
+        Some(Command::Run(opts)) => run(opts).unwrap_or_else(|e| exit_with(e)),
+        Some(Command::Headless(opts)) => headless(opts),
+        Some(Command::Record(opts)) => record_gif(&opts).unwrap_or_else(|e| exit_with(e)),
+        Some(Command::Convert(opts)) => convert(&opts).unwrap_or_else(|e| exit_with(e)),
+        Some(Command::SoupSearch(opts)) => soup_search(opts),
+        None => run(opts.run).unwrap_or_else(|e| exit_with(e)),
+    }
+}
+
+fn exit_with(message: impl Display) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+/// Writes the alive cells of generations to a file as they are computed, one
+/// `{"gen":n,"cells":[[x,y],...]}` line per generation.
+struct JsonlExport {
+    path: PathBuf,
+    writer: io::BufWriter<fs::File>,
+}
+
+impl JsonlExport {
+    fn create(path: &Path) -> Result<Self, String> {
+        let file = fs::File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: io::BufWriter::new(file),
+        })
+    }
+
+    fn write(&mut self, world: &automata::World) -> Result<(), String> {
+        let cells: Vec<String> = world
+            .live_coords()
+            .into_iter()
+            .map(|(x, y)| format!("[{},{}]", x, y))
+            .collect();
+        writeln!(
+            self.writer,
+            "{{\"gen\":{},\"cells\":[{}]}}",
+            world.generation(),
+            cells.join(",")
+        )
+        .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+fn headless(opts: HeadlessOpts) {
+    let replay = opts
+        .play_replay
+        .as_deref()
+        .map(|path| load_replay(path).unwrap_or_else(|e| exit_with(e)));
+    let mut world = match &replay {
+        Some(replay) => replay.initial_world(),
+        None => opts.world.build().unwrap_or_else(|e| exit_with(e)),
+    };
+    world.paused = false;
+    // Nothing is undone without a window
+    world.set_undo_limit(0);
+
+    let mut export = opts
+        .export_jsonl
+        .as_deref()
+        .map(|path| JsonlExport::create(path).unwrap_or_else(|e| exit_with(e)));
+    let mut write_generation = |world: &automata::World| {
+        if let Some(export) = &mut export {
+            export.write(world).unwrap_or_else(|e| exit_with(e));
+        }
+    };
+
+    write_generation(&world);
+    match replay {
+        Some(replay) => {
+            let mut player = replay.player();
+            while !player.is_finished() {
+                player.step(&mut world);
+                write_generation(&world);
+            }
+            // Edits made after the last step, if the replay has no steps
+            player.play(&mut world);
+        }
+        None => {
+            let engine = Engine::new(opts.gpu);
+            for _ in 0..opts.generations {
+                engine.step(&mut world);
+                write_generation(&world);
+            }
+        }
+    }
+    if let Some(export) = export {
+        export.finish().unwrap_or_else(|e| exit_with(e));
+    }
+
+    println!(
+        "generation {}, population {}",
+        world.generation(),
+        world.population()
+    );
+}
+
+fn record_gif(opts: &RecordOpts) -> Result<(), String> {
+    let mut world = opts.world.build()?;
+    // Nothing is undone without a window
+    world.set_undo_limit(0);
+    let options = theme::DrawOptions {
+        theme: match &opts.palette {
+            Some(path) => load_palette(path)?,
+            None => theme::Theme::default(),
+        },
+        transparent_dead: opts.transparent_dead,
+        ..theme::DrawOptions::default()
+    };
+
+    let frames = opts.generations as usize + 1;
+    let delay = Duration::from_millis(opts.gif_delay);
+    let mut recorder = recorder::Recorder::new(world.width(), world.height(), delay, frames);
+    recorder
+        .record(&world, &options)
+        .map_err(|e| e.to_string())?;
+    for _ in 0..opts.generations {
+        world.step();
+        recorder
+            .record(&world, &options)
+            .map_err(|e| e.to_string())?;
+    }
+    recorder
+        .save(&opts.output)
+        .map_err(|e| format!("{}: {}", opts.output.display(), e))
+}
+
+struct SoupResult {
+    seed: u64,
+    population: usize,
+    /// Generation at which the soup settled into a cycle, `None` if it was
+    /// still evolving after the last step
+    settled_at: Option<u64>,
+    period: Option<u64>,
+}
+
+fn soup_search(opts: SoupSearchOpts) {
+    if opts.width == 0 || opts.height == 0 {
+        exit_with("--width and --height must be at least 1");
+    }
+
+    let mut results: Vec<SoupResult> = (0..opts.count)
+        .map(|i| {
+            let seed = opts.seed.wrapping_add(i);
+            let mut world = automata::World::new(opts.width, opts.height, 0, rule::Rule::default());
+            world.stamp_centered(&random_soup(opts.soup_size, seed), automata::State::ALIVE);
+            world.paused = false;
+            world.set_undo_limit(0);
+
+            // Generation at which each state was first seen, to detect cycles
+            let mut seen = HashMap::new();
+            let mut settled_at = None;
+            let mut period = None;
+            for _ in 0..opts.steps {
+                seen.insert(world.state_hash(), world.generation());
+                world.update();
+                if let Some(&generation) = seen.get(&world.state_hash()) {
+                    settled_at = Some(generation);
+                    period = Some(world.generation() - generation);
+                    break;
+                }
+            }
+
+            SoupResult {
+                seed,
+                population: world.population(),
+                settled_at,
+                period,
+            }
+        })
+        .collect();
+
+    match opts.by {
+        SoupMetric::Population => results.sort_by_key(|r| Reverse(r.population)),
+        SoupMetric::Lifespan => results.sort_by_key(|r| Reverse(r.settled_at.unwrap_or(u64::MAX))),
+    }
+
+    let or_dash = |value: Option<u64>| value.map_or("-".to_string(), |v| v.to_string());
+    println!(
+        "{:>20} {:>10} {:>10} {:>8}",
+        "seed", "population", "settled", "period"
+    );
+    for result in results.iter().take(opts.top) {
+        println!(
+            "{:>20} {:>10} {:>10} {:>8}",
+            result.seed,
+            result.population,
+            or_dash(result.settled_at),
+            or_dash(result.period)
+        );
+    }
+    println!(
+        "Replay a soup with: run -w {} -h {} --soup-size {} --soup-seed <seed>",
+        opts.width, opts.height, opts.soup_size
+    );
+}
+
+fn format_of(path: &Path) -> Result<formats::Format, String> {
+    formats::Format::from_path(path)
+        .ok_or_else(|| format!("{}: unknown pattern format", path.display()))
+}
+
+/// Read a pattern file, or stdin when `path` is `-`.
+fn load_pattern(path: &Path, format: Option<formats::Format>) -> Result<formats::Pattern, String> {
+    let (text, format) = if path == Path::new("-") {
+        let format = format.ok_or("--format is required to read a pattern from stdin")?;
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("stdin: {}", e))?;
+        (text, format)
+    } else {
+        let format = format.map_or_else(|| format_of(path), Ok)?;
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        (text, format)
+    };
+
+    format
+        .parse(&text)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn load_replay(path: &Path) -> Result<replay::Replay, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    replay::Replay::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Add an edit made by the user to the session being recorded, if any,
+/// dropping the recording if the edit cannot be replayed.
+fn record(recording: &mut Option<replay::Replay>, event: replay::Event) {
+    if let Some(Err(e)) = recording.as_mut().map(|recording| recording.record(event)) {
+        error!("dropping the replay: {}", e);
+        *recording = None;
+    }
+}
+
+/// Set the cells of the brush centered on the cell at `index` to `state`,
+/// recording the edits. The world is checkpointed before the first cells of
+/// a `stroke` are painted, so the whole stroke is undone at once.
+fn paint(
+    world: &mut automata::World,
+    recording: &mut Option<replay::Replay>,
+    stroke: &mut bool,
+    index: usize,
+    brush_radius: usize,
+    state: automata::State,
+) {
+    if !*stroke {
+        world.checkpoint();
+        *stroke = true;
+    }
+    for index in world.square_around(index, brush_radius) {
+        world.set_cell_state(index, state);
+        record(recording, replay::Event::SetCell(index, state));
+    }
+}
+
+fn load_rule_table(path: &Path) -> Result<rule::TableRule, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let table = rule::TableRule::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let states = automata::State::TABLE_STATES.len();
+    if table.states as usize > states {
+        return Err(format!(
+            "{}: {} states, cells have at most {} (dead, alive, dying, conductor, head, tail)",
+            path.display(),
+            table.states,
+            states
+        ));
+    }
+    Ok(table)
+}
+
+fn load_palette(path: &Path) -> Result<theme::Theme, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    theme::Theme::from_palette(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn convert(opts: &ConvertOpts) -> Result<(), String> {
+    let to = format_of(&opts.to)?;
+    let pattern = load_pattern(&opts.from, opts.format)?;
+
+    fs::write(&opts.to, to.write(&pattern)).map_err(|e| format!("{}: {}", opts.to.display(), e))
+}
+
+/// Pause `world` if it is running and reached or went past the generation
+/// `pause_at`, returning whether it did. `pause_at` is cleared once it fired,
+/// so that the world can be resumed.
+fn pause_if_reached(world: &mut automata::World, pause_at: &mut Option<u64>) -> bool {
+    let reached = !world.paused && pause_at.is_some_and(|at| world.generation() >= at);
+    if reached {
+        world.paused = true;
+        *pause_at = None;
+    }
+    reached
+}
+
+/// Average durations of the sequential and parallel updates, measured on
+/// alternate generations while timing is enabled with `D`.
+#[derive(Default)]
+struct StepTimings {
+    sequential: Option<f64>,
+    parallel: Option<f64>,
+}
+
+impl StepTimings {
+    /// Weight of the previous average when adding a measure.
+    const SMOOTHING: f64 = 0.9;
+
+    /// Compute the next generation, alternating between the sequential and
+    /// the parallel update, and add its duration to the averages.
+    fn step(&mut self, world: &mut automata::World) {
+        let start = Instant::now();
+        let average = if world.generation().is_multiple_of(2) {
+            world.update_sequential();
+            &mut self.sequential
+        } else {
+            world.update_parallel();
+            &mut self.parallel
+        };
+
+        let micros = start.elapsed().as_secs_f64() * 1e6;
+        *average = Some(average.map_or(micros, |average| {
+            average * Self::SMOOTHING + micros * (1.0 - Self::SMOOTHING)
+        }));
+    }
+}
+
+/// Number of consecutive generations the population has stayed the same
+/// for. This is population stasis, not stability: blinkers and gliders keep
+/// the same population while they change.
+#[derive(Default)]
+struct PopulationStasis {
+    population: Option<usize>,
+    generations: u64,
+}
+
+impl PopulationStasis {
+    /// Add the population of the next generation, returning the number of
+    /// generations it has been the same for.
+    fn record(&mut self, population: usize) -> u64 {
+        if self.population == Some(population) {
+            self.generations += 1;
+        } else {
+            self.population = Some(population);
+            self.generations = 0;
+        }
+        self.generations
+    }
+}
+
+/// Figures of a generation shown in the window title and checked by the
+/// pause conditions.
+struct Stats {
+    automaton_name: String,
+    population: usize,
+    entropy: f64,
+    settled_period: Option<usize>,
+}
+
+impl Stats {
+    fn of(world: &automata::World) -> Self {
+        Self {
+            automaton_name: world.automaton_name(),
+            population: world.population(),
+            entropy: world.entropy(),
+            settled_period: world.settled_period(),
+        }
+    }
+}
+
+/// Generation computed in the background: the cells it changed, its stats,
+/// and its snapshot for the history.
+struct Generation {
+    changes: Vec<(usize, automata::State)>,
+    stats: Stats,
+    snapshot: automata::Snapshot,
+}
+
+/// Computes the generations of a world, on the GPU with --gpu.
+enum Engine {
+    Cpu,
+    #[cfg(feature = "gpu")]
+    Gpu(gpu::GpuStepper),
+}
+
+impl Engine {
+    /// Connect to the GPU if `gpu` is set, falling back to the CPU when
+    /// there is none or the `gpu` feature is disabled.
+    fn new(gpu: bool) -> Self {
+        if !gpu {
+            return Engine::Cpu;
+        }
+        #[cfg(feature = "gpu")]
+        match gpu::GpuStepper::new() {
+            Some(gpu) => return Engine::Gpu(gpu),
+            None => warn!("no GPU found, computing the generations on the CPU"),
+        }
+        #[cfg(not(feature = "gpu"))]
+        warn!("built without the `gpu` feature, computing the generations on the CPU");
+        Engine::Cpu
+    }
+
+    /// Compute the next generation of `world`, even when it is paused. The
+    /// GPU steps the worlds it supports only, the CPU stepping the others.
+    fn step(&self, world: &mut automata::World) {
+        match self {
+            Engine::Cpu => world.step(),
+            #[cfg(feature = "gpu")]
+            Engine::Gpu(gpu) => gpu.step(world),
+        }
+    }
+}
+
+enum Job {
+    /// Replace the world of the worker
+    Load(Box<automata::World>),
+    /// Compute the next generation of the world of the worker
+    Step,
+}
+
+/// Computes generations on a background thread, so a step longer than a
+/// frame does not freeze the window. The worker steps its own copy of the
+/// world and sends back the cells that changed along with the stats of the
+/// generation, which the main thread applies to its world. Edits are made to
+/// the main thread's world only: they cancel the generation being computed,
+/// so it is not applied over them, and the edited world is copied to the
+/// worker before the next one.
+struct Stepper {
+    jobs: Sender<(u64, Job)>,
+    generations: Receiver<(u64, Generation)>,
+    /// Identifier of the current job, the generations of earlier jobs being
+    /// stale
+    job: u64,
+    /// Whether the worker is computing a generation of the current job
+    busy: bool,
+    /// Whether the world of the worker is the same as the main thread's
+    synced: bool,
+}
+
+impl Stepper {
+    /// Spawn the worker, which wakes the event loop through `proxy` once a
+    /// generation is ready, and steps on the GPU if `gpu` is set.
+    fn spawn(proxy: EventLoopProxy<()>, gpu: bool) -> Self {
+        let (jobs, worker_jobs) = mpsc::channel::<(u64, Job)>();
+        let (worker_generations, generations) = mpsc::channel();
+        thread::spawn(move || {
+            let engine = Engine::new(gpu);
+            let mut world = None;
+            for (job, work) in worker_jobs {
+                let world = match (work, &mut world) {
+                    (Job::Load(loaded), _) => {
+                        let mut loaded = *loaded;
+                        // Only the main thread's world is edited and undone
+                        loaded.set_undo_limit(0);
+                        world = Some(loaded);
+                        continue;
+                    }
+                    (Job::Step, Some(world)) => world,
+                    (Job::Step, None) => continue,
+                };
+                engine.step(world);
+                let generation = Generation {
+                    changes: world.changes(),
+                    stats: Stats::of(world),
+                    snapshot: world.snapshot(),
+                };
+                if worker_generations.send((job, generation)).is_err()
+                    || proxy.send_event(()).is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            jobs,
+            generations,
+            job: 0,
+            busy: false,
+            synced: false,
+        }
+    }
+
+    /// Start computing the generation following `world`, unless one is
+    /// already being computed.
+    fn start(&mut self, world: &automata::World) {
+        if self.busy {
+            return;
+        }
+        if !self.synced {
+            self.synced = self
+                .jobs
+                .send((self.job, Job::Load(Box::new(world.clone()))))
+                .is_ok();
+        }
+        self.busy = self.synced && self.jobs.send((self.job, Job::Step)).is_ok();
+    }
+
+    /// Drop the generation being computed, if any, the world of the main
+    /// thread having been edited or stepped on its own.
+    fn cancel(&mut self) {
+        if self.busy {
+            self.job += 1;
+            self.busy = false;
+        }
+        self.synced = false;
+    }
+
+    /// The generation computed for the current job, if it is ready.
+    fn poll(&mut self) -> Option<Generation> {
+        while let Ok((job, generation)) = self.generations.try_recv() {
+            if job == self.job && self.busy {
+                self.busy = false;
+                return Some(generation);
+            }
+        }
+        None
+    }
+}
+
+impl Display for StepTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.sequential, self.parallel) {
+            (Some(sequential), Some(parallel)) => write!(
+                f,
+                "sequential {:.0}µs, parallel {:.0}µs, speedup {:.2}x",
+                sequential,
+                parallel,
+                sequential / parallel
+            ),
+            _ => write!(f, "timing..."),
+        }
+    }
+}
+
+/// Probability for a cell to be alive after a random fill with `X`.
+const RANDOM_FILL_DENSITY: f64 = 0.3;
+
+/// Numbers of generations computed by a press on `N`, cycled with `Tab`.
+const STEP_SIZES: [usize; 3] = [1, 5, 25];
+
+/// Shortest and longest times between two generations of a running world,
+/// halved and doubled with `+` and `-`.
+const MIN_STEP_INTERVAL: Duration = Duration::from_millis(16);
+const MAX_STEP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// File the world is saved to with `S`, and loaded from with `L`.
+const SAVE_PATH: &str = "world.cawb";
+
+/// Largest number of generations recorded in a GIF, recordings going over it
+/// being dropped.
+const MAX_GIF_FRAMES: usize = 1000;
+
+/// Keys stamping the built-in patterns, in the order of `patterns::LIBRARY`.
+const PATTERN_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// Largest radius of the brush painting cells, grown and shrunk with `]`
+/// and `[`.
+const MAX_BRUSH_RADIUS: usize = 16;
+
+/// Number of recent generations that can be scrubbed through.
+const HISTORY_LENGTH: usize = 100;
+
+fn run(opts: RunOpts) -> Result<(), RunError> {
+    if opts.cell_pixels == 0 {
+        exit_with("--cell-pixels must be at least 1");
+    }
+
+    let replay = opts
+        .play_replay
+        .as_deref()
+        .map(|path| load_replay(path).unwrap_or_else(|e| exit_with(e)));
+    let mut world = match &replay {
+        Some(replay) => replay.initial_world(),
+        None => opts.world.build().unwrap_or_else(|e| exit_with(e)),
+    };
+    let (mut width, mut height) = (world.width(), world.height());
+    let mut draw_options = theme::DrawOptions {
+        theme: match &opts.palette {
+            Some(path) => load_palette(path).unwrap_or_else(|e| exit_with(e)),
+            None => theme::Theme::default(),
+        },
+        ..theme::DrawOptions::default()
+    };
+    // Theme of the palette, or the default one, switched with the dark theme
+    // with `K`
+    let palette_theme = draw_options.theme;
 
     let event_loop = EventLoop::new();
     let icon = load_icon(Path::new("./icon.png"));
     let window = {
-        let size = LogicalSize::new(width as f64, width as f64);
+        let min_size = LogicalSize::new(width as f64, height as f64);
+        let size = LogicalSize::new(
+            (width * opts.cell_pixels) as f64,
+            (height * opts.cell_pixels) as f64,
+        );
         WindowBuilder::new()
             .with_title("Cellular Automata")
             .with_window_icon(icon)
             .with_inner_size(size)
-            .with_min_inner_size(size)
-            .build(&event_loop)
-            .unwrap()
+            .with_min_inner_size(min_size)
+            .build(&event_loop)?
     };
 
     let mut pixels = {
@@ -78,11 +1100,68 @@ fn main() -> Result<(), Error> {
     };
 
     let mut input = WinitInputHelper::new();
-    let mut world = automata::World::new(width, height);
+
+    // Session recorded with `--record-replay`, or replayed with `--play-replay`
+    let mut recording = opts
+        .record_replay
+        .as_ref()
+        .map(|_| replay::Replay::new(&world).unwrap_or_else(|e| exit_with(e)));
+    let mut player = replay.map(replay::Replay::player);
+    if player.as_ref().is_some_and(replay::Player::is_finished) {
+        player.take().unwrap().play(&mut world);
+    }
+
+    let mut pause_at = opts.run_for;
+    world.paused = pause_at.is_none();
+
+    // Visible coordinates of the keyboard cursor, shown once moved with the
+    // arrow keys
+    let mut cursor = (width / 2, height / 2);
+
+    // Most populated state seen since the world was last erased, restored
+    // with `B`
+    let mut best = world.snapshot();
+    let mut best_population = world.population();
+
+    let mut stasis = PopulationStasis::default();
+
+    let mut export = opts
+        .export_jsonl
+        .as_deref()
+        .map(|path| JsonlExport::create(path).unwrap_or_else(|e| exit_with(e)));
+    if let Some(export) = &mut export {
+        export.write(&world).unwrap_or_else(|e| exit_with(e));
+    }
+
+    // State painted by dragging with the left button, and last cell painted
+    let mut painting: Option<(automata::State, usize)> = None;
+    // Cells painted around the hovered one, in each direction
+    let mut brush_radius: usize = 0;
+
+    let mut step_size = STEP_SIZES[0];
+    let mut step_interval = MIN_STEP_INTERVAL;
+    let mut last_step = Instant::now();
+    let mut timings: Option<StepTimings> = None;
+    let mut stepper = Stepper::spawn(event_loop.create_proxy(), opts.gpu);
+    let mut stats = Stats::of(&world);
+
+    let mut history = history::History::new(HISTORY_LENGTH);
+    history.record(&world);
+    // Whether the world was edited since it was last recorded in the history
+    let mut unrecorded_edit = false;
+    // Whether the cells painted while the mouse buttons are held were
+    // checkpointed, to undo them at once
+    let mut stroke = false;
+
+    // GIF being recorded with `C`, and the file it is saved to
+    let mut gif: Option<(recorder::Recorder, String)> = None;
+
+    // Only redraw when the world changed, so a paused world leaves the app idle
+    let mut dirty = true;
 
     event_loop.run(move |event, _, control_flow| {
         if let Event::RedrawRequested(_) = event {
-            world.draw(pixels.get_frame());
+            world.draw(pixels.get_frame(), &draw_options);
             if pixels
                 .render()
                 .map_err(|e| error!("pixels.render() failed: {}", e))
@@ -95,36 +1174,890 @@ fn main() -> Result<(), Error> {
 
         if input.update(&event) {
             if input.key_pressed(VirtualKeyCode::Escape) || input.quit() {
+                if let Some(Err(e)) = export.take().map(JsonlExport::finish) {
+                    error!("{}", e);
+                }
+                if let (Some(recording), Some(path)) = (&recording, &opts.record_replay) {
+                    if let Err(e) = fs::write(path, recording.write()) {
+                        error!("{}: {}", path.display(), e);
+                    }
+                }
                 *control_flow = ControlFlow::Exit;
                 return;
             }
 
             if input.key_pressed(VirtualKeyCode::Space) {
                 world.paused = !world.paused;
+                dirty = true;
             }
 
             if input.key_pressed(VirtualKeyCode::E) {
-                world = automata::World::new(width, height);
+                world.checkpoint();
+                world.clear();
+                record(&mut recording, replay::Event::Clear);
+                best = world.snapshot();
+                best_population = 0;
+                history.clear();
+                history.record(&world);
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::S) {
+                if let Err(e) = world.save(Path::new(SAVE_PATH)) {
+                    error!("{}: {}", SAVE_PATH, e);
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::P) {
+                // The keyboard cursor is left out of screenshots
+                let options = theme::DrawOptions {
+                    cursor: None,
+                    transparent_dead: opts.transparent_dead,
+                    ..draw_options
+                };
+                let path = format!("frame-{}.png", world.generation());
+                if let Err(e) = world.save_png(Path::new(&path), &options) {
+                    error!("{}: {}", path, e);
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::C) {
+                match gif.take() {
+                    Some((recorder, path)) => {
+                        if let Err(e) = recorder.save(Path::new(&path)) {
+                            error!("{}: {}", path, e);
+                        }
+                    }
+                    None => {
+                        let delay = Duration::from_millis(opts.gif_delay);
+                        let mut recorder =
+                            recorder::Recorder::new(width, height, delay, MAX_GIF_FRAMES);
+                        let options = theme::DrawOptions {
+                            cursor: None,
+                            transparent_dead: opts.transparent_dead,
+                            ..draw_options
+                        };
+                        match recorder.record(&world, &options) {
+                            Ok(()) => {
+                                gif = Some((recorder, format!("run-{}.gif", world.generation())))
+                            }
+                            Err(e) => warn!("dropping the GIF: {}", e),
+                        }
+                    }
+                }
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::L) {
+                match automata::World::load(Path::new(SAVE_PATH)) {
+                    Ok(saved) if (saved.width(), saved.height()) == (width, height) => {
+                        // Loading starts over from the saved cells and rule,
+                        // keeping the other settings of the world
+                        world.checkpoint();
+                        world.clear();
+                        world.automaton = automata::Automaton::LifeLike;
+                        world.rule = saved.rule;
+                        world.stamp(&saved.live_coords(), (0, 0), automata::State::ALIVE);
+                        record(&mut recording, replay::Event::Clear);
+                        record(
+                            &mut recording,
+                            replay::Event::Automaton(world.automaton.clone(), world.rule),
+                        );
+                        record(&mut recording, replay::Event::Restore(world.snapshot()));
+                        best = world.snapshot();
+                        best_population = world.population();
+                        history.clear();
+                        history.record(&world);
+                        dirty = true;
+                    }
+                    Ok(saved) => warn!(
+                        "{}: cannot load a {}x{} world in a {}x{} window",
+                        SAVE_PATH,
+                        saved.width(),
+                        saved.height(),
+                        width,
+                        height
+                    ),
+                    Err(e) => error!("{}: {}", SAVE_PATH, e),
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::B) {
+                world.checkpoint();
+                world.restore(&best);
+                record(&mut recording, replay::Event::Restore(best.clone()));
+                unrecorded_edit = true;
+                dirty = true;
+            }
+
+            // Undoing and redoing goes through the edits and the generations
+            // checkpointed by the world, pausing it as scrubbing does
+            let control = input.held_control();
+            let undone = if control && input.key_pressed(VirtualKeyCode::Z) {
+                world.undo()
+            } else if control && input.key_pressed(VirtualKeyCode::Y) {
+                world.redo()
+            } else {
+                false
+            };
+            if undone {
+                record(&mut recording, replay::Event::Restore(world.snapshot()));
+                unrecorded_edit = true;
+                world.paused = true;
+                dirty = true;
+            }
+
+            // Going back and forth through the history of the generations
+            let scrubbed = if input.key_pressed(VirtualKeyCode::Comma) {
+                history.back()
+            } else if input.key_pressed(VirtualKeyCode::Period) {
+                history.forward()
+            } else if input.key_pressed(VirtualKeyCode::Home) {
+                let oldest = history.range().map(|(oldest, _)| oldest);
+                oldest.and_then(|oldest| history.seek(oldest))
+            } else if input.key_pressed(VirtualKeyCode::End) {
+                let newest = history.range().map(|(_, newest)| newest);
+                newest.and_then(|newest| history.seek(newest))
+            } else {
+                None
+            };
+            if let Some(snapshot) = scrubbed {
+                world.restore(snapshot);
+                record(&mut recording, replay::Event::Restore(snapshot.clone()));
+                world.paused = true;
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::X) {
+                // Seeded from the clock, each fill is different; replays
+                // record the resulting cells rather than the seed
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+                world.checkpoint();
+                world.randomize_seeded(RANDOM_FILL_DENSITY, seed);
+                record(&mut recording, replay::Event::Restore(world.snapshot()));
+                unrecorded_edit = true;
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::H) {
+                world.checkpoint();
+                world.flip_horizontal();
+                record(&mut recording, replay::Event::FlipHorizontal);
+                unrecorded_edit = true;
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::V) {
+                world.checkpoint();
+                world.flip_vertical();
+                record(&mut recording, replay::Event::FlipVertical);
+                unrecorded_edit = true;
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::T) {
+                // Non-square worlds are not rotated, nor checkpointed
+                if world.width() == world.height() {
+                    world.checkpoint();
+                }
+                match world.rotate90() {
+                    Ok(()) => {
+                        record(&mut recording, replay::Event::Rotate);
+                        unrecorded_edit = true;
+                        dirty = true;
+                    }
+                    Err(e) => warn!("{}", e),
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::A) {
+                draw_options.age_colors = !draw_options.age_colors;
+                dirty = true;
+            }
+
+            let (x, y) = cursor;
+            let moved = if input.key_pressed(VirtualKeyCode::Left) {
+                Some((x.saturating_sub(1), y))
+            } else if input.key_pressed(VirtualKeyCode::Right) {
+                Some(((x + 1).min(width - 1), y))
+            } else if input.key_pressed(VirtualKeyCode::Up) {
+                Some((x, y.saturating_sub(1)))
+            } else if input.key_pressed(VirtualKeyCode::Down) {
+                Some((x, (y + 1).min(height - 1)))
+            } else {
+                None
+            };
+            if let Some((x, y)) = moved {
+                cursor = (x, y);
+                draw_options.cursor = Some(world.index(x, y));
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::Return) {
+                if let Some(index) = draw_options.cursor {
+                    let state = match world.cell_state(index) {
+                        Some(automata::State::ALIVE) => automata::State::DEAD,
+                        _ => automata::State::ALIVE,
+                    };
+                    world.checkpoint();
+                    world.set_cell_state(index, state);
+                    record(&mut recording, replay::Event::SetCell(index, state));
+                    unrecorded_edit = true;
+                    dirty = true;
+                }
+            }
+
+            if input.key_pressed(VirtualKeyCode::F) {
+                if let Some(index) = mouse_index(&mut input, &mut pixels, &world) {
+                    world.checkpoint();
+                    if world.is_frozen(index) {
+                        world.unfreeze(index);
+                        record(&mut recording, replay::Event::Unfreeze(index));
+                    } else {
+                        world.freeze(index);
+                        record(&mut recording, replay::Event::Freeze(index));
+                    }
+                    unrecorded_edit = true;
+                    dirty = true;
+                }
+            }
+
+            // Sparks an electron on the hovered cell, the wires of Wireworld
+            // being painted as alive cells
+            if input.key_pressed(VirtualKeyCode::I) {
+                if let Some(index) = mouse_index(&mut input, &mut pixels, &world) {
+                    world.checkpoint();
+                    world.set_cell_state(index, automata::State::HEAD);
+                    record(
+                        &mut recording,
+                        replay::Event::SetCell(index, automata::State::HEAD),
+                    );
+                    unrecorded_edit = true;
+                    dirty = true;
+                }
+            }
+
+            // Number keys stamp a built-in pattern with its top-left corner
+            // on the hovered cell, or on the keyboard cursor
+            let preset = PATTERN_KEYS
+                .iter()
+                .zip(patterns::LIBRARY)
+                .find(|(&key, _)| input.key_pressed(key));
+            if let Some((_, (_, pattern))) = preset {
+                let origin = mouse_coords(&mut input, &mut pixels).unwrap_or(cursor);
+                world.checkpoint();
+                world.stamp(&pattern(), origin, automata::State::ALIVE);
+                record(&mut recording, replay::Event::Restore(world.snapshot()));
+                unrecorded_edit = true;
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::LBracket) {
+                brush_radius = brush_radius.saturating_sub(1);
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::RBracket) {
+                brush_radius = (brush_radius + 1).min(MAX_BRUSH_RADIUS);
+                dirty = true;
             }
 
-            if input.mouse_held(0) {
-                if let Some(index) = mouse_index(&mut input, &mut pixels, width) {
-                    world.set_cell_state(index, automata::State::ALIVE);
+            // A left click toggles the hovered cell, painting the brush
+            // around it with the state it was toggled to, dragging then
+            // paints the cells met with that state
+            let mut painted = false;
+            if input.mouse_pressed(0) {
+                if let Some(index) = mouse_index(&mut input, &mut pixels, &world) {
+                    let state = match world.cell_state(index) {
+                        Some(automata::State::ALIVE) => automata::State::DEAD,
+                        _ => automata::State::ALIVE,
+                    };
+                    paint(
+                        &mut world,
+                        &mut recording,
+                        &mut stroke,
+                        index,
+                        brush_radius,
+                        state,
+                    );
+                    painting = Some((state, index));
+                    painted = true;
+                }
+            } else if input.mouse_held(0) {
+                let index = mouse_index(&mut input, &mut pixels, &world);
+                if let (Some((state, last)), Some(index)) = (painting, index) {
+                    if index != last {
+                        paint(
+                            &mut world,
+                            &mut recording,
+                            &mut stroke,
+                            index,
+                            brush_radius,
+                            state,
+                        );
+                        painting = Some((state, index));
+                        painted = true;
+                    }
                 }
             }
+            if input.mouse_released(0) {
+                painting = None;
+            }
 
             if input.mouse_held(1) {
-                if let Some(index) = mouse_index(&mut input, &mut pixels, width) {
-                    world.set_cell_state(index, automata::State::DEAD);
+                if let Some(index) = mouse_index(&mut input, &mut pixels, &world) {
+                    let state = automata::State::DEAD;
+                    paint(
+                        &mut world,
+                        &mut recording,
+                        &mut stroke,
+                        index,
+                        brush_radius,
+                        state,
+                    );
+                    painted = true;
+                }
+            }
+
+            // Middle dragging builds walls of immutable cells
+            if input.mouse_held(2) {
+                if let Some(index) = mouse_index(&mut input, &mut pixels, &world) {
+                    let state = automata::State::IMMUTABLE;
+                    paint(
+                        &mut world,
+                        &mut recording,
+                        &mut stroke,
+                        index,
+                        brush_radius,
+                        state,
+                    );
+                    painted = true;
                 }
             }
+            dirty |= painted;
+            unrecorded_edit |= painted;
+
+            // Edits are recorded once the mouse buttons are released, so a
+            // whole stroke is undone at once
+            let mouse_held = (0..3).any(|button| input.mouse_held(button));
+            stroke &= mouse_held;
+            if unrecorded_edit && !mouse_held {
+                history.record(&world);
+                unrecorded_edit = false;
+            }
 
             if let Some(size) = input.window_resized() {
+                // The world follows the size of the window, keeping the size
+                // of the cells, unless a replay is recorded or played
+                let logical = size.to_logical::<f64>(window.scale_factor());
+                let columns = (logical.width as usize / opts.cell_pixels).max(1);
+                let rows = (logical.height as usize / opts.cell_pixels).max(1);
+                if recording.is_none() && player.is_none() && (columns, rows) != (width, height) {
+                    world.resize(columns, rows);
+                    if gif.take().is_some() {
+                        warn!("dropping the GIF: the world was resized");
+                    }
+                    width = columns;
+                    height = rows;
+                    cursor = (cursor.0.min(width - 1), cursor.1.min(height - 1));
+                    draw_options.cursor =
+                        draw_options.cursor.map(|_| world.index(cursor.0, cursor.1));
+                    best = world.snapshot();
+                    best_population = world.population();
+                    history.clear();
+                    history.record(&world);
+
+                    let surface = Surface::create(&window);
+                    let surface_texture = SurfaceTexture::new(size.width, size.height, surface);
+                    match Pixels::new(width as u32, height as u32, surface_texture) {
+                        Ok(resized) => pixels = resized,
+                        Err(e) => {
+                            error!("cannot resize the pixel buffer: {}", e);
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                    }
+                }
                 pixels.resize(size.width, size.height);
+                dirty = true;
             }
 
-            world.update();
-            window.request_redraw();
+            if input.key_pressed(VirtualKeyCode::W) {
+                draw_options.seams = !draw_options.seams;
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::K) {
+                draw_options.theme = if draw_options.theme == palette_theme {
+                    theme::Theme::dark()
+                } else {
+                    palette_theme
+                };
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::G) {
+                draw_options.trail = match draw_options.trail {
+                    0 => opts.trail.min(automata::MAX_TRAIL),
+                    _ => 0,
+                };
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::D) {
+                timings = match timings {
+                    Some(_) => None,
+                    None => Some(StepTimings::default()),
+                };
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::R) {
+                world.cycle_automaton();
+                record(
+                    &mut recording,
+                    replay::Event::Automaton(world.automaton.clone(), world.rule),
+                );
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::M) {
+                world.set_neighbourhood(world.neighbourhood().toggled());
+                record(
+                    &mut recording,
+                    replay::Event::Neighbourhood(world.neighbourhood()),
+                );
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::O) {
+                world.set_boundary(world.boundary().cycled());
+                record(&mut recording, replay::Event::Boundary(world.boundary()));
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::Equals) || input.key_pressed(VirtualKeyCode::Add) {
+                step_interval = (step_interval / 2).max(MIN_STEP_INTERVAL);
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::Minus)
+                || input.key_pressed(VirtualKeyCode::Subtract)
+            {
+                step_interval = (step_interval * 2).min(MAX_STEP_INTERVAL);
+                dirty = true;
+            }
+
+            if input.key_pressed(VirtualKeyCode::Tab) {
+                step_size = STEP_SIZES
+                    .iter()
+                    .copied()
+                    .find(|&size| size > step_size)
+                    .unwrap_or(STEP_SIZES[0]);
+                dirty = true;
+            }
+
+            // Worlds starting at or scrubbed past the generation of --run-for
+            // pause before computing any generation
+            pause_if_reached(&mut world, &mut pause_at);
+            // Running computes one generation every `step_interval`, `N`
+            // computes `step_size` of them at once, stopping early on a still
+            // world
+            let stepping = world.paused && input.key_pressed(VirtualKeyCode::N);
+            // Frames painting cells do not compute a generation, so the
+            // automaton does not undo the edit before it is drawn
+            let steps = if painted {
+                0
+            } else if !world.paused {
+                (last_step.elapsed() >= step_interval) as usize
+            } else if stepping {
+                step_size
+            } else {
+                0
+            };
+            // Running generations are computed in the background, the others
+            // on this thread: replays and timings need to step the world
+            // themselves, and `N` steps while paused
+            let background = !world.paused && player.is_none() && timings.is_none();
+            // Generations computed while the world was edited are outdated
+            if dirty {
+                stepper.cancel();
+                stats = Stats::of(&world);
+            } else if !background {
+                stepper.cancel();
+            }
+            for _ in 0..steps {
+                if background {
+                    match stepper.poll() {
+                        Some(generation) => {
+                            world.apply_changes(&generation.changes);
+                            stats = generation.stats;
+                            history.push(generation.snapshot);
+                        }
+                        None => break,
+                    }
+                } else {
+                    match (&mut player, &mut timings) {
+                        (Some(player), _) => player.step(&mut world),
+                        (None, Some(timings)) => timings.step(&mut world),
+                        (None, None) => world.step(),
+                    }
+                    stats = Stats::of(&world);
+                    history.record(&world);
+                }
+                last_step = Instant::now();
+                if let Some(recording) = &mut recording {
+                    recording.record_step();
+                }
+                if stats.population > best_population {
+                    best = world.snapshot();
+                    best_population = stats.population;
+                }
+                if let Some((recorder, _)) = &mut gif {
+                    let options = theme::DrawOptions {
+                        cursor: None,
+                        transparent_dead: opts.transparent_dead,
+                        ..draw_options
+                    };
+                    if let Err(e) = recorder.record(&world, &options) {
+                        warn!("dropping the GIF: {}", e);
+                        gif = None;
+                    }
+                }
+                if let Some(Err(e)) = export.as_mut().map(|export| export.write(&world)) {
+                    error!("{}", e);
+                    export = None;
+                }
+                dirty = true;
+
+                if pause_if_reached(&mut world, &mut pause_at) {
+                    break;
+                }
+                if player.as_ref().is_some_and(replay::Player::is_finished) {
+                    // Hand the world over to the user once the replay is over
+                    player = None;
+                    world.paused = true;
+                    break;
+                }
+                if stepping && world.changed_indices().is_empty() {
+                    break;
+                }
+                if stats.population == 0 {
+                    world.paused = true;
+                    break;
+                }
+                if opts.pause_when_stable && stats.settled_period.is_some() {
+                    world.paused = true;
+                    break;
+                }
+                let unchanged_for = stasis.record(stats.population);
+                if opts.pause_on_stasis && unchanged_for >= opts.stasis_generations {
+                    // Resuming runs for as many generations again
+                    stasis = PopulationStasis::default();
+                    world.paused = true;
+                    break;
+                }
+            }
+            if background && !world.paused && !painted {
+                stepper.start(&world);
+            }
+
+            if dirty {
+                let mut title = format!(
+                    "Cellular Automata - {} - generation {}, population {}, entropy {:.3}, step {}",
+                    stats.automaton_name,
+                    world.generation(),
+                    stats.population,
+                    stats.entropy,
+                    step_size
+                );
+                if world.neighbourhood() == automata::Neighbourhood::VonNeumann {
+                    title.push_str(" - von Neumann neighbourhood");
+                }
+                if world.boundary() != automata::Boundary::Toroidal {
+                    title.push_str(&format!(" - {} boundary", world.boundary()));
+                }
+                if stats.population == 0 && world.generation() > 0 {
+                    title.push_str(" - population extinct");
+                } else if stats.settled_period == Some(1) {
+                    title.push_str(" - stable");
+                } else if let Some(period) = stats.settled_period {
+                    title.push_str(&format!(" - period {} oscillator", period));
+                }
+                if brush_radius > 0 {
+                    let side = 2 * brush_radius + 1;
+                    title.push_str(&format!(" - brush {}x{}", side, side));
+                }
+                if step_interval > MIN_STEP_INTERVAL {
+                    title.push_str(&format!(
+                        " - {}ms per generation",
+                        step_interval.as_millis()
+                    ));
+                }
+                if let Some((recorder, _)) = &gif {
+                    title.push_str(&format!(" - recording {} frames", recorder.frame_count()));
+                }
+                if let Some(timings) = &timings {
+                    title.push_str(&format!(" - {}", timings));
+                }
+                window.set_title(&title);
+                window.request_redraw();
+                dirty = false;
+            }
+
+            // A running world wakes the loop up when its next generation is
+            // due, or once the worker computed it if it is late
+            let due = last_step + step_interval;
+            *control_flow = if world.paused || (stepper.busy && due <= Instant::now()) {
+                ControlFlow::Wait
+            } else {
+                ControlFlow::WaitUntil(due)
+            };
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Longest stretch of generations the population of `pattern` stays the
+    /// same for over `generations` generations, as counted by
+    /// `PopulationStasis`.
+    fn longest_stasis(pattern: &[(usize, usize)], generations: usize) -> u64 {
+        let mut world = automata::World::new(60, 40, 0, rule::Rule::default());
+        world.stamp(pattern, (4, 4), automata::State::ALIVE);
+        let mut stasis = PopulationStasis::default();
+        (0..generations)
+            .map(|_| {
+                world.step();
+                stasis.record(world.population())
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn stasis_is_reached_by_still_lifes_and_oscillators_only() {
+        assert_eq!(longest_stasis(&patterns::block(), 11), 10);
+        // Population stasis, not stability: the blinker keeps changing
+        assert_eq!(longest_stasis(&patterns::blinker(), 11), 10);
+        assert!(longest_stasis(&patterns::gosper_glider_gun(), 120) < 10);
+    }
+
+    #[test]
+    fn run_for_pauses_at_the_target_generation() {
+        let mut world = automata::World::new(8, 8, 0, rule::Rule::default());
+        world.stamp(&patterns::glider(), (0, 0), automata::State::ALIVE);
+        world.paused = false;
+        let mut pause_at = Some(5);
+
+        while !world.paused {
+            world.update();
+            pause_if_reached(&mut world, &mut pause_at);
+        }
+        assert_eq!(world.generation(), 5);
+        world.update();
+        assert_eq!(world.generation(), 5);
+        // Resuming runs on
+        world.paused = false;
+        assert!(!pause_if_reached(&mut world, &mut pause_at));
+        world.update();
+        assert_eq!(world.generation(), 6);
+
+        // --run-for 0 pauses before the first generation
+        let mut world = automata::World::new(8, 8, 0, rule::Rule::default());
+        world.paused = false;
+        assert!(pause_if_reached(&mut world, &mut Some(0)));
+        assert!(world.paused);
+
+        // Worlds gone past the target pause as soon as they run
+        world.paused = false;
+        world.update();
+        world.update();
+        assert!(pause_if_reached(&mut world, &mut Some(1)));
+        assert_eq!(world.generation(), 2);
+    }
+
+    #[test]
+    fn start_patterns_are_stamped_at_the_center() {
+        let build = |args: &[&str]| WorldOpts::try_parse_from(args).unwrap().build();
+
+        let world = build(&["world", "-w", "20", "-h", "20", "--start-pattern", "block"]).unwrap();
+        assert_eq!(world.live_coords(), [(9, 9), (10, 9), (9, 10), (10, 10)]);
+        for name in patterns::names() {
+            let world = build(&["world", "--start-pattern", name]).unwrap();
+            assert_eq!(world.population(), patterns::by_name(name).unwrap().len());
+        }
+        match build(&["world", "--start-pattern", "nope"]) {
+            Err(error) => assert!(error.contains("glider"), "{}", error),
+            Ok(_) => panic!("unknown patterns should be refused"),
+        }
+    }
+
+    #[test]
+    fn jsonl_export_writes_a_line_per_generation() {
+        let path = std::env::temp_dir().join(format!("export-{}.jsonl", std::process::id()));
+        let mut world = automata::World::new(5, 5, 0, rule::Rule::default());
+        world.stamp(&patterns::blinker(), (1, 2), automata::State::ALIVE);
+
+        let mut export = JsonlExport::create(&path).unwrap();
+        export.write(&world).unwrap();
+        world.step();
+        export.write(&world).unwrap();
+        export.finish().unwrap();
+        let text = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            text.unwrap(),
+            "{\"gen\":0,\"cells\":[[1,2],[2,2],[3,2]]}\n{\"gen\":1,\"cells\":[[2,1],[2,2],[2,3]]}\n"
+        );
+    }
+
+    #[test]
+    fn record_writes_a_frame_per_generation() {
+        use image::AnimationDecoder;
+
+        let path = std::env::temp_dir().join(format!("record-{}.gif", std::process::id()));
+        let args = ["record", "--start-pattern", "glider", "-n", "4", "-o"];
+        let opts = RecordOpts::try_parse_from(args.iter().copied().chain(path.to_str())).unwrap();
+
+        let recorded = record_gif(&opts);
+        let file = fs::File::open(&path);
+        let frames = file.map(|file| {
+            let decoder = image::gif::GifDecoder::new(io::BufReader::new(file)).unwrap();
+            decoder.into_frames().count()
+        });
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recorded, Ok(()));
+        assert_eq!(frames.unwrap(), 5);
+    }
+
+    #[test]
+    fn brush_strokes_paint_squares_undone_at_once() {
+        let mut world = automata::World::new(10, 10, 1, rule::Rule::default());
+        let mut recording = Some(replay::Replay::new(&world).unwrap());
+        let mut stroke = false;
+        let (alive, corner, center) =
+            (automata::State::ALIVE, world.index(0, 0), world.index(5, 5));
+
+        paint(&mut world, &mut recording, &mut stroke, corner, 1, alive);
+        assert_eq!(world.live_coords(), [(0, 0), (1, 0), (0, 1), (1, 1)]);
+        paint(&mut world, &mut recording, &mut stroke, center, 0, alive);
+        assert_eq!(world.population(), 5);
+
+        assert!(world.undo());
+        assert!(world.is_empty());
+        let replayed = recording.unwrap();
+        let mut replayed_world = replayed.initial_world();
+        replayed.player().play(&mut replayed_world);
+        assert_eq!(replayed_world.population(), 5);
+    }
+
+    #[test]
+    fn subcommands_are_parsed() {
+        let parse = |args: &[&str]| Opts::try_parse_from(args).ok().map(|opts| opts.command);
+
+        assert!(matches!(parse(&["ca"]), Some(None)));
+        // Flags given without a subcommand are those of `run`, as before
+        // subcommands were added
+        assert!(matches!(
+            parse(&["ca", "-w", "250", "-h", "200"]),
+            Some(None)
+        ));
+        let opts = Opts::try_parse_from(["ca", "-w", "250", "-h", "200"]).ok();
+        assert!(matches!(
+            opts.map(|opts| opts.run.world),
+            Some(WorldOpts {
+                width: 250,
+                height: 200,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse(&["ca", "run", "-w", "20"]),
+            Some(Some(Command::Run(RunOpts {
+                world: WorldOpts { width: 20, .. },
+                ..
+            })))
+        ));
+        assert!(matches!(
+            parse(&["ca", "headless", "-n", "7"]),
+            Some(Some(Command::Headless(HeadlessOpts { generations: 7, .. })))
+        ));
+        assert!(matches!(
+            parse(&["ca", "record", "-n", "5", "-o", "glider.gif"]),
+            Some(Some(Command::Record(RecordOpts { generations: 5, .. })))
+        ));
+        assert!(matches!(
+            parse(&["ca", "soup-search", "--count", "3"]),
+            Some(Some(Command::SoupSearch(SoupSearchOpts { count: 3, .. })))
+        ));
+        assert!(parse(&["ca", "convert", "--from", "a.rle"]).is_none());
+        assert!(parse(&["ca", "fly"]).is_none());
+    }
+
+    #[test]
+    fn zero_sizes_are_refused() {
+        let build = |args: &[&str]| WorldOpts::try_parse_from(args).unwrap().build();
+
+        assert!(build(&["world", "-w", "0"]).is_err());
+        assert!(build(&["world", "-h", "0"]).is_err());
+        let world = build(&["world", "-w", "7", "-h", "3"]).unwrap();
+        assert_eq!((world.width(), world.height()), (7, 3));
+    }
+
+    #[test]
+    fn fitted_worlds_take_the_size_of_the_pattern() {
+        let path = std::env::temp_dir().join(format!("fit-{}.rle", std::process::id()));
+        fs::write(&path, "x = 9, y = 9, rule = B3/S23\nbo$2bo$3o!\n").unwrap();
+        let pattern = path.to_str().unwrap();
+        let build = |args: &[&str]| WorldOpts::try_parse_from(args).unwrap().build();
+
+        let fitted = build(&["world", "--pattern", pattern, "--fit-pattern"]);
+        let margin = build(&["world", "-p", pattern, "--fit-pattern", "--fit-margin", "2"]);
+        let unfitted = build(&["world", "-p", pattern, "-w", "30", "-h", "20"]);
+        fs::remove_file(&path).unwrap();
+        let (fitted, margin, unfitted) = (fitted.unwrap(), margin.unwrap(), unfitted.unwrap());
+        assert_eq!((fitted.width(), fitted.height()), (9, 9));
+        assert_eq!(fitted.population(), 5);
+        assert_eq!((margin.width(), margin.height()), (13, 13));
+        assert_eq!((unfitted.width(), unfitted.height()), (30, 20));
+
+        assert!(build(&["world", "--fit-pattern"]).is_err());
+    }
+
+    #[test]
+    fn radius_two_runs_larger_than_life_rules() {
+        let build = |args: &[&str]| WorldOpts::try_parse_from(args).unwrap().build();
+        let square: Vec<_> = (0..3).flat_map(|y| (0..3).map(move |x| (x, y))).collect();
+        let args = ["world", "-w", "12", "-h", "12", "--radius", "2", "--rule"];
+        let with_rule = |rule| build(&[&args[..], &[rule]].concat()).unwrap();
+
+        // Each cell of a 3x3 square counts the 8 others, the cells around it
+        // at most 6: the square is a still life of B9-11/S8-12
+        let mut world = with_rule("B9-11/S8-12");
+        assert_eq!(world.radius(), 2);
+        world.stamp(&square, (4, 4), automata::State::ALIVE);
+        world.step();
+        assert_eq!(world.population(), 9);
+
+        // With B6-8, the 3 cells along each side of the square are born
+        let mut world = with_rule("B6-8/S8-12");
+        world.stamp(&square, (4, 4), automata::State::ALIVE);
+        world.step();
+        assert_eq!(world.population(), 9 + 4 * 3);
+        assert_eq!(
+            world.cell_state(world.index(3, 5)),
+            Some(automata::State::ALIVE)
+        );
+        assert_eq!(
+            world.cell_state(world.index(3, 3)),
+            Some(automata::State::DEAD)
+        );
+
+        assert!(build(&["world", "--radius", "2", "--neighbourhood", "von-neumann"]).is_ok());
+    }
+}
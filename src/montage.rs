@@ -0,0 +1,148 @@
+//! Several independent worlds stepped together and drawn side by side, e.g.
+//! to compare seeds or rules.
+
+use crate::automata::World;
+use crate::theme::DrawOptions;
+
+/// Color of the lines separating the worlds of a montage.
+const SEPARATOR: [u8; 4] = [0x80, 0x80, 0x80, 0xFF];
+
+/// Worlds laid out in rows of `columns` tiles, separated by lines of
+/// `separator` pixels. Each tile is as large as the largest world, smaller
+/// worlds being drawn in its top-left corner.
+pub struct Montage {
+    columns: usize,
+    separator: usize,
+    worlds: Vec<World>,
+}
+
+impl Montage {
+    pub fn new(columns: usize, separator: usize, worlds: Vec<World>) -> Self {
+        Self {
+            columns: columns.max(1),
+            separator,
+            worlds,
+        }
+    }
+
+    pub fn worlds(&self) -> &[World] {
+        &self.worlds
+    }
+
+    pub fn worlds_mut(&mut self) -> &mut [World] {
+        &mut self.worlds
+    }
+
+    fn rows(&self) -> usize {
+        self.worlds.len().div_ceil(self.columns)
+    }
+
+    /// Width and height of a tile.
+    fn tile_size(&self) -> (usize, usize) {
+        let width = self.worlds.iter().map(World::width).max().unwrap_or(0);
+        let height = self.worlds.iter().map(World::height).max().unwrap_or(0);
+        (width, height)
+    }
+
+    /// Width of the frame drawn by `draw`, in pixels.
+    pub fn width(&self) -> usize {
+        let columns = self.columns.min(self.worlds.len());
+        let (tile_width, _) = self.tile_size();
+        columns * tile_width + columns.saturating_sub(1) * self.separator
+    }
+
+    /// Height of the frame drawn by `draw`, in pixels.
+    pub fn height(&self) -> usize {
+        let rows = self.rows();
+        let (_, tile_height) = self.tile_size();
+        rows * tile_height + rows.saturating_sub(1) * self.separator
+    }
+
+    /// Compute the next generation of every world, unless it is paused.
+    pub fn update(&mut self) {
+        for world in &mut self.worlds {
+            world.update();
+        }
+    }
+
+    /// Compute the next generation of every world, even when paused.
+    pub fn step(&mut self) {
+        for world in &mut self.worlds {
+            world.step();
+        }
+    }
+
+    /// Draw the worlds to a frame buffer of `width` by `height` pixels.
+    pub fn draw(&self, frame: &mut [u8], options: &DrawOptions) {
+        let width = self.width();
+        let (tile_width, tile_height) = self.tile_size();
+
+        for pixel in frame.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&SEPARATOR);
+        }
+
+        for (i, world) in self.worlds.iter().enumerate() {
+            let left = (i % self.columns) * (tile_width + self.separator);
+            let top = (i / self.columns) * (tile_height + self.separator);
+            let mut tile = vec![0; world.width() * world.height() * 4];
+            world.draw(&mut tile, options);
+
+            for y in 0..tile_height {
+                for x in 0..tile_width {
+                    let color = if x < world.width() && y < world.height() {
+                        let start = (y * world.width() + x) * 4;
+                        &tile[start..start + 4]
+                    } else {
+                        &options.theme.dead[..]
+                    };
+                    let start = ((top + y) * width + left + x) * 4;
+                    frame[start..start + 4].copy_from_slice(color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::State;
+    use crate::rule::Rule;
+
+    #[test]
+    fn stepping_advances_every_world() {
+        let worlds = vec![
+            World::new(4, 4, 0, Rule::default()),
+            World::new(6, 3, 1, Rule::parse("B36/S23").unwrap()),
+            World::new(5, 5, 0, Rule::default()),
+        ];
+        let mut montage = Montage::new(2, 1, worlds);
+        montage.step();
+        montage.step();
+        assert!(montage.worlds().iter().all(|world| world.generation() == 2));
+
+        montage.worlds_mut()[1].paused = false;
+        montage.update();
+        let generations: Vec<u64> = montage.worlds().iter().map(World::generation).collect();
+        assert_eq!(generations, [2, 3, 2]);
+    }
+
+    #[test]
+    fn worlds_are_drawn_in_tiles() {
+        let mut first = World::new(2, 2, 0, Rule::default());
+        first.set_cell_state(first.index(1, 1), State::ALIVE);
+        let second = World::new(1, 2, 0, Rule::default());
+        let montage = Montage::new(2, 1, vec![first, second]);
+        assert_eq!((montage.width(), montage.height()), (5, 2));
+
+        let mut frame = vec![0; 5 * 2 * 4];
+        let options = DrawOptions::default();
+        montage.draw(&mut frame, &options);
+        let pixel = |x: usize, y: usize| &frame[(y * 5 + x) * 4..(y * 5 + x) * 4 + 4];
+        assert_eq!(pixel(1, 1), options.theme.alive);
+        assert_eq!(pixel(2, 0), SEPARATOR);
+        assert_eq!(pixel(3, 1), options.theme.dead);
+        // Padding of the smaller world
+        assert_eq!(pixel(4, 0), options.theme.dead);
+    }
+}
@@ -0,0 +1,192 @@
+//! Built-in patterns, as `(x, y)` coordinates of their live cells relative to
+//! the top-left corner of their bounding box.
+
+use crate::random::Rng;
+
+pub fn block() -> Vec<(usize, usize)> {
+    vec![(0, 0), (1, 0), (0, 1), (1, 1)]
+}
+
+pub fn blinker() -> Vec<(usize, usize)> {
+    vec![(0, 0), (1, 0), (2, 0)]
+}
+
+pub fn glider() -> Vec<(usize, usize)> {
+    vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+}
+
+/// Lightweight spaceship, moving left.
+pub fn lwss() -> Vec<(usize, usize)> {
+    vec![
+        (1, 0),
+        (4, 0),
+        (0, 1),
+        (0, 2),
+        (4, 2),
+        (0, 3),
+        (1, 3),
+        (2, 3),
+        (3, 3),
+    ]
+}
+
+/// Period 3 oscillator, made of bars of 3 cells along rows and columns 0, 5,
+/// 7 and 12 of a 13 by 13 box.
+pub fn pulsar() -> Vec<(usize, usize)> {
+    let lines = [0, 5, 7, 12];
+    let bars = [2, 3, 4, 8, 9, 10];
+
+    lines
+        .iter()
+        .flat_map(|&line| {
+            bars.iter()
+                .flat_map(move |&bar| vec![(bar, line), (line, bar)])
+        })
+        .collect()
+}
+
+/// Gosper glider gun, 36 by 9 cells, firing a glider toward the bottom right
+/// every 30 generations.
+pub fn gosper_glider_gun() -> Vec<(usize, usize)> {
+    vec![
+        (24, 0),
+        (22, 1),
+        (24, 1),
+        (12, 2),
+        (13, 2),
+        (20, 2),
+        (21, 2),
+        (34, 2),
+        (35, 2),
+        (11, 3),
+        (15, 3),
+        (20, 3),
+        (21, 3),
+        (34, 3),
+        (35, 3),
+        (0, 4),
+        (1, 4),
+        (10, 4),
+        (16, 4),
+        (20, 4),
+        (21, 4),
+        (0, 5),
+        (1, 5),
+        (10, 5),
+        (14, 5),
+        (16, 5),
+        (17, 5),
+        (22, 5),
+        (24, 5),
+        (10, 6),
+        (16, 6),
+        (24, 6),
+        (11, 7),
+        (15, 7),
+        (12, 8),
+        (13, 8),
+    ]
+}
+
+/// Random `width` by `height` soup, each cell being alive with probability
+/// `density`.
+pub fn soup(width: usize, height: usize, density: f64, rng: &mut Rng) -> Vec<(usize, usize)> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|_| rng.gen_bool(density))
+        .collect()
+}
+
+type Preset = fn() -> Vec<(usize, usize)>;
+
+/// Named patterns, as accepted by `--start-pattern`.
+pub const LIBRARY: &[(&str, Preset)] = &[
+    ("block", block),
+    ("blinker", blinker),
+    ("glider", glider),
+    ("gosper-gun", gosper_glider_gun),
+    ("lwss", lwss),
+    ("pulsar", pulsar),
+];
+
+pub fn by_name(name: &str) -> Option<Vec<(usize, usize)>> {
+    LIBRARY
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, pattern)| pattern())
+}
+
+pub fn names() -> Vec<&'static str> {
+    LIBRARY.iter().map(|(name, _)| *name).collect()
+}
+
+/// Width and height of the pattern bounding box.
+pub fn size(pattern: &[(usize, usize)]) -> (usize, usize) {
+    pattern.iter().fold((0, 0), |(width, height), &(x, y)| {
+        (width.max(x + 1), height.max(y + 1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::{State, World};
+    use crate::rule::Rule;
+
+    #[test]
+    fn presets_have_their_known_cell_counts() {
+        let counts = [
+            ("block", 4),
+            ("blinker", 3),
+            ("glider", 5),
+            ("lwss", 9),
+            ("pulsar", 48),
+        ];
+        for &(name, count) in &counts {
+            let pattern = by_name(name).unwrap();
+            let mut cells = pattern.clone();
+            cells.sort_unstable();
+            cells.dedup();
+            assert_eq!(cells.len(), count, "{}", name);
+            assert_eq!(pattern.len(), count, "{}", name);
+        }
+        assert_eq!(size(&pulsar()), (13, 13));
+        assert_eq!(size(&lwss()), (5, 4));
+    }
+
+    #[test]
+    fn stamping_near_the_edge_wraps_around() {
+        let mut world = World::new(10, 10, 0, Rule::default());
+        world.stamp(&lwss(), (8, 8), State::ALIVE);
+
+        assert_eq!(world.population(), 9);
+        assert_eq!(world.cell_state(world.index(9, 8)), Some(State::ALIVE));
+        assert_eq!(world.cell_state(world.index(2, 8)), Some(State::ALIVE));
+    }
+
+    #[test]
+    fn gosper_gun_fits_the_default_world() {
+        let gun = gosper_glider_gun();
+        assert_eq!(gun.len(), 36);
+        assert_eq!(size(&gun), (36, 9));
+
+        let mut world = World::new(150, 100, 0, Rule::default());
+        world.stamp_centered(&gun, State::ALIVE);
+        assert_eq!(world.population(), 36);
+    }
+
+    #[test]
+    fn gosper_gun_fires_a_glider_every_30_generations() {
+        let mut world = World::new(150, 100, 0, Rule::default());
+        world.stamp(&gosper_glider_gun(), (2, 2), State::ALIVE);
+
+        // The gun returns to its first phase every 30 generations, with one
+        // more glider of 5 cells flying away from it
+        for gliders in 1..=4 {
+            for _ in 0..30 {
+                world.step();
+            }
+            assert_eq!(world.population(), 36 + 5 * gliders);
+        }
+    }
+}
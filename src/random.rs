@@ -0,0 +1,80 @@
+//! Deterministic pseudo-random numbers for the features relying on chance
+//! (random soups, soup search, random fills).
+//!
+//! Stability guarantee: a given seed always yields the same sequence of
+//! numbers, on every platform and with every version of this crate. The
+//! generator only relies on wrapping 64 bits integer arithmetic, so seeds
+//! logged by a run (e.g. by `soup-search`) can be replayed anywhere. Changing
+//! the algorithm is a breaking change.
+
+/// Seedable pseudo-random generator (xorshift64*, seeded through splitmix64).
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed (splitmix64) so close seeds give unrelated
+        // sequences, and so the state is never zero
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Self {
+            state: if z == 0 { 0x9E37_79B9_7F4A_7C15 } else { z },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniformly distributed in `[0, 1)`, built from the 53 high bits of
+    /// `next_u64` so it is exact and identical everywhere.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `true` with probability `p`.
+    pub fn gen_bool(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_seeds_give_equal_sequences() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        for _ in 0..1000 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(7).next_u64(), Rng::new(8).next_u64());
+    }
+
+    #[test]
+    fn sequences_never_change() {
+        // Part of the stability guarantee: these values must stay the same
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.next_u64(), 0x31B0_ECE7_C4F6_97A2);
+        assert_eq!(rng.next_u64(), 0x9008_A3B1_CB68_6F03);
+        assert_eq!(rng.next_u64(), 0x7C71_73AB_D97B_E16F);
+    }
+
+    #[test]
+    fn floats_stay_in_the_unit_interval() {
+        let mut rng = Rng::new(0);
+        assert!((0..1000)
+            .map(|_| rng.next_f64())
+            .all(|x| (0.0..1.0).contains(&x)));
+        assert!(!(0..100).any(|_| rng.gen_bool(0.0)));
+        assert!((0..100).all(|_| rng.gen_bool(1.0)));
+    }
+}
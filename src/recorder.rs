@@ -0,0 +1,146 @@
+//! Recording of the generations of a `World` as an animated GIF.
+
+use crate::automata::World;
+use crate::theme::DrawOptions;
+use image::gif::Encoder as GifEncoder;
+use image::{Delay, Frame, ImageResult, RgbaImage};
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Returned when recording more frames than a `Recorder` can hold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TooManyFrames {
+    pub max_frames: usize,
+}
+
+impl fmt::Display for TooManyFrames {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "recording exceeds {} frames", self.max_frames)
+    }
+}
+
+impl error::Error for TooManyFrames {}
+
+/// Frames of the visible area of a world drawn as by `World::draw`, one
+/// pixel per cell, kept in memory until they are written. At most
+/// `max_frames` frames are held, to bound the memory used.
+pub struct Recorder {
+    width: usize,
+    height: usize,
+    delay: Duration,
+    max_frames: usize,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recorder {
+    /// Start a recording of `width` by `height` frames, each shown for
+    /// `delay` in the GIF.
+    pub fn new(width: usize, height: usize, delay: Duration, max_frames: usize) -> Self {
+        Self {
+            width,
+            height,
+            delay,
+            max_frames,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Add a frame of `world`, which must be the size of the recording.
+    pub fn record(&mut self, world: &World, options: &DrawOptions) -> Result<(), TooManyFrames> {
+        if self.frames.len() >= self.max_frames {
+            return Err(TooManyFrames {
+                max_frames: self.max_frames,
+            });
+        }
+
+        let mut frame = vec![0; self.width * self.height * 4];
+        world.draw(&mut frame, options);
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Encode the frames recorded as an animated GIF.
+    pub fn write(self, writer: impl Write) -> ImageResult<()> {
+        let delay = Delay::from_numer_denom_ms(self.delay.as_millis() as u32, 1);
+        let mut encoder = GifEncoder::new(writer);
+
+        for frame in self.frames {
+            let image = RgbaImage::from_raw(self.width as u32, self.height as u32, frame)
+                .expect("frames are drawn to the size of the recording");
+            encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+        }
+        Ok(())
+    }
+
+    /// Write the GIF to the file at `path`.
+    pub fn save(self, path: &Path) -> ImageResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::State;
+    use crate::rule::Rule;
+    use image::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    fn blinker() -> World {
+        let mut world = World::new(5, 5, 0, Rule::default());
+        world.stamp(&[(1, 2), (2, 2), (3, 2)], (0, 0), State::ALIVE);
+        world
+    }
+
+    #[test]
+    fn records_one_frame_per_generation() {
+        let mut world = blinker();
+        let options = DrawOptions::default();
+        let mut recorder = Recorder::new(5, 5, Duration::from_millis(100), 10);
+        for _ in 0..3 {
+            recorder.record(&world, &options).unwrap();
+            world.step();
+        }
+        assert_eq!(recorder.frame_count(), 3);
+
+        let mut gif = Vec::new();
+        recorder.write(&mut gif).unwrap();
+        let frames = GifDecoder::new(&gif[..])
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].delay(), Delay::from_numer_denom_ms(100, 1));
+        let alive = options.theme.alive;
+        assert_eq!(frames[0].buffer().get_pixel(1, 2).0, alive);
+        assert_eq!(frames[1].buffer().get_pixel(2, 1).0, alive);
+        assert_ne!(frames[1].buffer().get_pixel(1, 2).0, alive);
+    }
+
+    #[test]
+    fn recording_past_the_cap_is_refused() {
+        let world = blinker();
+        let options = DrawOptions::default();
+        let mut recorder = Recorder::new(5, 5, Duration::from_millis(100), 2);
+        recorder.record(&world, &options).unwrap();
+        recorder.record(&world, &options).unwrap();
+
+        assert_eq!(
+            recorder.record(&world, &options),
+            Err(TooManyFrames { max_frames: 2 })
+        );
+        assert_eq!(recorder.frame_count(), 2);
+    }
+}
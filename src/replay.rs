@@ -0,0 +1,736 @@
+//! Recording of an interactive session, as its initial world and the edits
+//! made to it, replayed deterministically by stepping the world and applying
+//! the edits at the moments they were made.
+//!
+//! Replays are text files starting with a `#Replay 1` line, followed by the
+//! initial world and by one line per edit, prefixed by the number of steps
+//! computed before it:
+//!
+//! ```text
+//! #Replay 1
+//! size 150 100 0
+//! automaton life-like B3/S23 exclusive
+//! neighbourhood moore 1
+//! boundary toroidal
+//! max-population none
+//! cells 15151:alive 15152:alive 15153:alive
+//! frozen
+//! inactive
+//! 12 set 15000 alive
+//! 40 clear
+//! end 100
+//! ```
+
+use crate::automata::{Automaton, Boundary, Neighbourhood, Snapshot, State, World};
+use crate::rule::Rule;
+use log::warn;
+use std::error;
+use std::fmt;
+
+/// Edit made to the world during a session.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    SetCell(usize, State),
+    Freeze(usize),
+    Unfreeze(usize),
+    Clear,
+    FlipHorizontal,
+    FlipVertical,
+    Rotate,
+    /// Switch to another automaton, and rule when it is Life-like
+    Automaton(Automaton, Rule),
+    Neighbourhood(Neighbourhood),
+    Boundary(Boundary),
+    /// Bring the world back to an earlier state
+    Restore(Snapshot),
+}
+
+impl Event {
+    pub fn apply(&self, world: &mut World) {
+        match self {
+            Event::SetCell(index, state) => world.set_cell_state(*index, *state),
+            Event::Freeze(index) => world.freeze(*index),
+            Event::Unfreeze(index) => world.unfreeze(*index),
+            Event::Clear => world.clear(),
+            Event::FlipHorizontal => world.flip_horizontal(),
+            Event::FlipVertical => world.flip_vertical(),
+            Event::Rotate => {
+                if let Err(e) = world.rotate90() {
+                    warn!("replay: {}", e);
+                }
+            }
+            Event::Automaton(automaton, rule) => {
+                world.automaton = automaton.clone();
+                world.rule = *rule;
+            }
+            Event::Neighbourhood(neighbourhood) => world.set_neighbourhood(*neighbourhood),
+            Event::Boundary(boundary) => world.set_boundary(*boundary),
+            Event::Restore(snapshot) => world.restore(snapshot),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ReplayError {}
+
+/// Returned when recording a session running an automaton that cannot be
+/// written to a replay, such as a table or custom rule: only the automata
+/// whose name is parsed back to them can be replayed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnnamedAutomatonError {
+    pub automaton: String,
+}
+
+impl fmt::Display for UnnamedAutomatonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot record a session of a {} automaton, only named automata can be replayed",
+            self.automaton
+        )
+    }
+}
+
+impl error::Error for UnnamedAutomatonError {}
+
+/// Refuse the automata whose name does not parse back to them.
+fn check_named(automaton: &Automaton) -> Result<(), UnnamedAutomatonError> {
+    match automaton.to_string().parse::<Automaton>() {
+        Ok(parsed) if parsed == *automaton => Ok(()),
+        _ => Err(UnnamedAutomatonError {
+            automaton: automaton.to_string(),
+        }),
+    }
+}
+
+/// Initial world of a session and the edits made to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    width: usize,
+    height: usize,
+    gutter: usize,
+    automaton: Automaton,
+    rule: Rule,
+    neighbourhood: Neighbourhood,
+    radius: usize,
+    boundary: Boundary,
+    max_population: Option<usize>,
+    /// Indexes and states of the cells that are not dead
+    cells: Vec<(usize, State)>,
+    frozen: Vec<usize>,
+    inactive: Vec<usize>,
+    /// Edits, along with the number of steps computed before each
+    events: Vec<(u64, Event)>,
+    /// Number of steps computed during the session
+    steps: u64,
+}
+
+/// Line made of `name` followed by `indexes`.
+fn indexes_line(name: &str, indexes: &[usize]) -> String {
+    let mut line = name.to_string();
+    for index in indexes {
+        line.push_str(&format!(" {}", index));
+    }
+    line.push('\n');
+    line
+}
+
+/// Line made of `name` followed by `cells` as `INDEX:STATE` words.
+fn cells_line(name: &str, cells: &[(usize, State)]) -> String {
+    let mut line = name.to_string();
+    for &(index, state) in cells {
+        line.push_str(&format!(" {}:{}", index, state_name(state)));
+    }
+    line
+}
+
+fn state_name(state: State) -> &'static str {
+    match state {
+        State::ALIVE => "alive",
+        State::DEAD => "dead",
+        State::CONDUCTOR => "conductor",
+        State::HEAD => "head",
+        State::TAIL => "tail",
+        State::DYING => "dying",
+        State::IMMUTABLE => "immutable",
+    }
+}
+
+/// State written as `name` by `state_name`.
+fn parse_state(name: &str) -> Option<State> {
+    [
+        State::ALIVE,
+        State::DEAD,
+        State::CONDUCTOR,
+        State::HEAD,
+        State::TAIL,
+        State::DYING,
+        State::IMMUTABLE,
+    ]
+    .iter()
+    .copied()
+    .find(|&state| state_name(state) == name)
+}
+
+impl Replay {
+    /// Start recording a session from the current state of `world`, which
+    /// must run a named automaton (see `Automaton`'s `Display`).
+    pub fn new(world: &World) -> Result<Self, UnnamedAutomatonError> {
+        check_named(&world.automaton)?;
+        let indexes = |filter: &dyn Fn(usize) -> bool| -> Vec<usize> {
+            (0..world.cell_count())
+                .filter(|&index| filter(index))
+                .collect()
+        };
+
+        Ok(Self {
+            width: world.width(),
+            height: world.height(),
+            gutter: world.gutter(),
+            automaton: world.automaton.clone(),
+            rule: world.rule,
+            neighbourhood: world.neighbourhood(),
+            radius: world.radius(),
+            boundary: world.boundary(),
+            max_population: world.max_population,
+            cells: (0..world.cell_count())
+                .filter_map(|index| match world.cell_state(index) {
+                    Some(State::DEAD) | None => None,
+                    Some(state) => Some((index, state)),
+                })
+                .collect(),
+            frozen: indexes(&|index| world.is_frozen(index)),
+            inactive: indexes(&|index| !world.is_active(index)),
+            events: Vec::new(),
+            steps: 0,
+        })
+    }
+
+    /// Record an edit made after the steps recorded so far, refusing
+    /// switches to an automaton that cannot be named.
+    pub fn record(&mut self, event: Event) -> Result<(), UnnamedAutomatonError> {
+        if let Event::Automaton(automaton, _) = &event {
+            check_named(automaton)?;
+        }
+        self.events.push((self.steps, event));
+        Ok(())
+    }
+
+    /// Record that a generation was computed.
+    pub fn record_step(&mut self) {
+        self.steps += 1;
+    }
+
+    /// Number of steps computed during the session.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The world as it was when the recording started.
+    pub fn initial_world(&self) -> World {
+        let mut world = World::new(self.width, self.height, self.gutter, self.rule);
+        world.automaton = self.automaton.clone();
+        world.set_neighbourhood(self.neighbourhood);
+        world.set_radius(self.radius);
+        world.set_boundary(self.boundary);
+        world.max_population = self.max_population;
+        if !self.inactive.is_empty() {
+            let mut mask = vec![true; world.cell_count()];
+            for &index in &self.inactive {
+                if let Some(active) = mask.get_mut(index) {
+                    *active = false;
+                }
+            }
+            world.set_active_mask(Some(mask));
+        }
+        for &(index, state) in &self.cells {
+            world.set_cell_state(index, state);
+        }
+        for &index in &self.frozen {
+            world.freeze(index);
+        }
+        world
+    }
+
+    /// Player replaying the session from its initial world.
+    pub fn player(self) -> Player {
+        Player {
+            replay: self,
+            next_event: 0,
+            steps: 0,
+        }
+    }
+
+    pub fn write(&self) -> String {
+        let mut text = String::from("#Replay 1\n");
+        text.push_str(&format!(
+            "size {} {} {}\n",
+            self.width, self.height, self.gutter
+        ));
+        text.push_str(&format!(
+            "automaton {} {} {}\n",
+            self.automaton,
+            self.rule,
+            if self.rule.include_center {
+                "inclusive"
+            } else {
+                "exclusive"
+            }
+        ));
+        text.push_str(&format!(
+            "neighbourhood {} {}\n",
+            self.neighbourhood, self.radius
+        ));
+        text.push_str(&format!("boundary {}\n", self.boundary));
+        match self.max_population {
+            Some(max_population) => text.push_str(&format!("max-population {}\n", max_population)),
+            None => text.push_str("max-population none\n"),
+        }
+        text.push_str(&cells_line("cells", &self.cells));
+        text.push('\n');
+        text.push_str(&indexes_line("frozen", &self.frozen));
+        text.push_str(&indexes_line("inactive", &self.inactive));
+
+        for (steps, event) in &self.events {
+            let line = match event {
+                Event::SetCell(index, state) => format!("set {} {}", index, state_name(*state)),
+                Event::Freeze(index) => format!("freeze {}", index),
+                Event::Unfreeze(index) => format!("unfreeze {}", index),
+                Event::Clear => "clear".to_string(),
+                Event::FlipHorizontal => "flip-horizontal".to_string(),
+                Event::FlipVertical => "flip-vertical".to_string(),
+                Event::Rotate => "rotate".to_string(),
+                Event::Automaton(automaton, rule) => format!("automaton {} {}", automaton, rule),
+                Event::Neighbourhood(neighbourhood) => format!("neighbourhood {}", neighbourhood),
+                Event::Boundary(boundary) => format!("boundary {}", boundary),
+                Event::Restore(snapshot) => {
+                    let restore = format!("restore {}", snapshot.generation());
+                    let mut line = cells_line(&restore, &snapshot.cells());
+                    if !snapshot.frozen().is_empty() {
+                        line.push_str(" frozen");
+                        for index in snapshot.frozen() {
+                            line.push_str(&format!(" {}", index));
+                        }
+                    }
+                    line
+                }
+            };
+            text.push_str(&format!("{} {}\n", steps, line));
+        }
+        text.push_str(&format!("end {}\n", self.steps));
+
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(line, content)| (line + 1, content.trim()))
+            .filter(|(_, content)| !content.is_empty());
+        let mut last_line = 0;
+        let mut next = |expected: &str| {
+            let (line, content) = lines.next().ok_or_else(|| ReplayError {
+                line: last_line,
+                message: format!("missing `{}` line", expected),
+            })?;
+            last_line = line;
+            let mut words = content.split_whitespace();
+            if words.next() != Some(expected) {
+                return Err(ReplayError {
+                    line,
+                    message: format!("expected `{}`, got `{}`", expected, content),
+                });
+            }
+            Ok((line, words.map(str::to_string).collect::<Vec<_>>()))
+        };
+
+        let (line, version) = next("#Replay")?;
+        if version != ["1"] {
+            return Err(ReplayError {
+                line,
+                message: "unsupported replay version".to_string(),
+            });
+        }
+
+        let (line, size) = next("size")?;
+        let size = parse_numbers::<usize>(line, &size)?;
+        let (width, height, gutter) = match size[..] {
+            [width, height, gutter] => (width, height, gutter),
+            _ => return Err(error(line, "expected `size WIDTH HEIGHT GUTTER`")),
+        };
+        let cell_count = (width + 2 * gutter) * (height + 2 * gutter);
+
+        let (line, automaton) = next("automaton")?;
+        let (automaton, rule) = match &automaton[..] {
+            [automaton, rule, center] => {
+                let (automaton, rule) = parse_automaton(line, automaton, rule)?;
+                let include_center = match center.as_str() {
+                    "inclusive" => true,
+                    "exclusive" => false,
+                    _ => return Err(error(line, "expected `inclusive` or `exclusive`")),
+                };
+                (
+                    automaton,
+                    Rule {
+                        include_center,
+                        ..rule
+                    },
+                )
+            }
+            _ => return Err(error(line, "expected `automaton NAME RULE CENTER`")),
+        };
+
+        let (line, neighbourhood) = next("neighbourhood")?;
+        let (neighbourhood, radius) = match &neighbourhood[..] {
+            [neighbourhood, radius] => (
+                parse_neighbourhood(line, neighbourhood)?,
+                parse_numbers::<usize>(line, &[radius])?[0],
+            ),
+            _ => return Err(error(line, "expected `neighbourhood NAME RADIUS`")),
+        };
+
+        let (line, boundary) = next("boundary")?;
+        let boundary = match &boundary[..] {
+            [boundary] => parse_boundary(line, boundary)?,
+            _ => return Err(error(line, "expected `boundary NAME`")),
+        };
+
+        let (line, max_population) = next("max-population")?;
+        let max_population = match &max_population[..] {
+            [none] if none == "none" => None,
+            [max_population] => Some(
+                max_population
+                    .parse()
+                    .map_err(|_| error(line, "expected a population or `none`"))?,
+            ),
+            _ => {
+                return Err(error(
+                    line,
+                    "expected `max-population N` or `max-population none`",
+                ))
+            }
+        };
+
+        let (line, cells) = next("cells")?;
+        let cells = cells
+            .iter()
+            .map(|cell| parse_cell(line, cell, cell_count))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut indexes = |name: &str| -> Result<Vec<usize>, ReplayError> {
+            let (line, indexes) = next(name)?;
+            let indexes = parse_numbers::<usize>(line, &indexes)?;
+            match indexes.iter().find(|&&index| index >= cell_count) {
+                Some(index) => Err(error(line, &format!("no cell at index {}", index))),
+                None => Ok(indexes),
+            }
+        };
+        let frozen = indexes("frozen")?;
+        let inactive = indexes("inactive")?;
+
+        let mut events = Vec::new();
+        for (line, content) in lines {
+            last_line = line;
+            let words: Vec<&str> = content.split_whitespace().collect();
+            if let ["end", steps] = words[..] {
+                return Ok(Self {
+                    width,
+                    height,
+                    gutter,
+                    automaton,
+                    rule,
+                    neighbourhood,
+                    radius,
+                    boundary,
+                    max_population,
+                    cells,
+                    frozen,
+                    inactive,
+                    events,
+                    steps: parse_numbers(line, &[steps])?[0],
+                });
+            }
+
+            let steps = parse_numbers::<u64>(line, &words[..1])?[0];
+            let index = |word: &str| -> Result<usize, ReplayError> {
+                let index = parse_numbers::<usize>(line, &[word])?[0];
+                if index < cell_count {
+                    Ok(index)
+                } else {
+                    Err(error(line, &format!("no cell at index {}", index)))
+                }
+            };
+            let event = match words[1..] {
+                ["set", cell, state] => match parse_state(state) {
+                    Some(state) => Event::SetCell(index(cell)?, state),
+                    None => return Err(error(line, &format!("unknown state `{}`", state))),
+                },
+                ["freeze", cell] => Event::Freeze(index(cell)?),
+                ["unfreeze", cell] => Event::Unfreeze(index(cell)?),
+                ["clear"] => Event::Clear,
+                ["flip-horizontal"] => Event::FlipHorizontal,
+                ["flip-vertical"] => Event::FlipVertical,
+                ["rotate"] => Event::Rotate,
+                ["automaton", automaton, rule] => {
+                    let (automaton, rule) = parse_automaton(line, automaton, rule)?;
+                    Event::Automaton(automaton, rule)
+                }
+                ["neighbourhood", neighbourhood] => {
+                    Event::Neighbourhood(parse_neighbourhood(line, neighbourhood)?)
+                }
+                ["boundary", boundary] => Event::Boundary(parse_boundary(line, boundary)?),
+                ["restore", generation, ref rest @ ..] => {
+                    let generation = parse_numbers::<u64>(line, &[generation])?[0];
+                    let (cells, frozen) = match rest.iter().position(|&word| word == "frozen") {
+                        Some(split) => (&rest[..split], &rest[split + 1..]),
+                        None => (rest, &[][..]),
+                    };
+                    let cells = cells
+                        .iter()
+                        .map(|cell| parse_cell(line, cell, cell_count))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let frozen = frozen
+                        .iter()
+                        .map(|&cell| index(cell))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Event::Restore(Snapshot::from_cells(
+                        generation, cell_count, &cells, &frozen,
+                    ))
+                }
+                _ => return Err(error(line, &format!("unknown event `{}`", content))),
+            };
+            events.push((steps, event));
+        }
+
+        Err(error(last_line, "missing `end` line"))
+    }
+}
+
+fn error(line: usize, message: &str) -> ReplayError {
+    ReplayError {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Cell written as `INDEX:STATE` by `cells_line`. Replays written before
+/// the states were kept list the alive cells as `INDEX` in the `cells` line
+/// and as `INDEX:AGE` in `restore` lines.
+fn parse_cell(line: usize, cell: &str, cell_count: usize) -> Result<(usize, State), ReplayError> {
+    let mut parts = cell.splitn(2, ':');
+    let index = parse_numbers::<usize>(line, &[parts.next().unwrap_or_default()])?[0];
+    if index >= cell_count {
+        return Err(error(line, &format!("no cell at index {}", index)));
+    }
+    match parts.next() {
+        None => Ok((index, State::ALIVE)),
+        Some(state) => match parse_state(state) {
+            Some(state) => Ok((index, state)),
+            None if state.parse::<u32>().is_ok() => Ok((index, State::ALIVE)),
+            None => Err(error(line, &format!("unknown state `{}`", state))),
+        },
+    }
+}
+
+fn parse_numbers<T: std::str::FromStr>(
+    line: usize,
+    words: &[impl AsRef<str>],
+) -> Result<Vec<T>, ReplayError> {
+    words
+        .iter()
+        .map(|word| {
+            let word = word.as_ref();
+            word.parse()
+                .map_err(|_| error(line, &format!("invalid number `{}`", word)))
+        })
+        .collect()
+}
+
+fn parse_automaton(
+    line: usize,
+    automaton: &str,
+    rule: &str,
+) -> Result<(Automaton, Rule), ReplayError> {
+    let automaton = automaton.parse().map_err(|e: String| error(line, &e))?;
+    let rule = Rule::parse(rule).map_err(|e| error(line, &e.to_string()))?;
+    Ok((automaton, rule))
+}
+
+/// Steps a world through a replay, applying its edits on the way.
+pub struct Player {
+    replay: Replay,
+    next_event: usize,
+    steps: u64,
+}
+
+impl Player {
+    /// Whether every step of the session has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.steps >= self.replay.steps
+    }
+
+    /// Apply the edits made before the next step, then compute it, unless
+    /// the replay is finished. Edits made after the last step are applied
+    /// once it is reached.
+    pub fn step(&mut self, world: &mut World) {
+        if !self.is_finished() {
+            self.apply_events(world);
+            world.step();
+            self.steps += 1;
+        }
+        if self.is_finished() {
+            self.apply_events(world);
+        }
+    }
+
+    fn apply_events(&mut self, world: &mut World) {
+        while let Some((steps, event)) = self.replay.events.get(self.next_event) {
+            if *steps > self.steps {
+                break;
+            }
+            event.apply(world);
+            self.next_event += 1;
+        }
+    }
+
+    /// Replay the whole session on `world`.
+    pub fn play(mut self, world: &mut World) {
+        self.apply_events(world);
+        while !self.is_finished() {
+            self.step(world);
+        }
+    }
+}
+
+fn parse_neighbourhood(line: usize, neighbourhood: &str) -> Result<Neighbourhood, ReplayError> {
+    neighbourhood.parse().map_err(|e: String| error(line, &e))
+}
+
+fn parse_boundary(line: usize, boundary: &str) -> Result<Boundary, ReplayError> {
+    boundary.parse().map_err(|e: String| error(line, &e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patterns;
+    use crate::rule::{BlockRule, TableRule};
+
+    #[test]
+    fn named_automata_are_replayed() {
+        let mut world = World::new(8, 8, 0, Rule::default());
+        world.automaton = Automaton::Margolus(BlockRule::critters());
+        world.stamp(&patterns::glider(), (2, 2), State::ALIVE);
+        let mut replay = Replay::new(&world).unwrap();
+        replay.record_step();
+        replay
+            .record(Event::Automaton(Automaton::BriansBrain, Rule::default()))
+            .unwrap();
+        replay.record_step();
+
+        assert_eq!(Replay::parse(&replay.write()), Ok(replay));
+    }
+
+    #[test]
+    fn unnamed_automata_are_refused() {
+        let table = TableRule::parse("states 2\n0 1:3 -> 1").unwrap();
+        let mut world = World::new(8, 8, 0, Rule::default());
+        world.automaton = Automaton::Table(table.clone());
+        assert_eq!(
+            Replay::new(&world),
+            Err(UnnamedAutomatonError {
+                automaton: "table".to_string()
+            })
+        );
+
+        world.automaton = Automaton::LifeLike;
+        let mut replay = Replay::new(&world).unwrap();
+        let block_rule = BlockRule { table: [0; 16] };
+        assert!(replay
+            .record(Event::Automaton(
+                Automaton::Margolus(block_rule),
+                Rule::default()
+            ))
+            .is_err());
+        assert!(replay
+            .record(Event::Automaton(Automaton::Table(table), Rule::default()))
+            .is_err());
+    }
+
+    #[test]
+    fn restored_snapshots_keep_their_states_and_frozen_cells() {
+        let mut world = World::new(6, 6, 0, Rule::default());
+        let mut replay = Replay::new(&world).unwrap();
+        world.set_cell_state(3, State::HEAD);
+        world.set_cell_state(9, State::IMMUTABLE);
+        world.freeze(9);
+        world.freeze(20);
+        replay.record(Event::Restore(world.snapshot())).unwrap();
+
+        let text = replay.write();
+        assert!(
+            text.contains("0 restore 0 3:head 9:immutable frozen 9 20\n"),
+            "{}",
+            text
+        );
+        assert_eq!(Replay::parse(&text), Ok(replay));
+    }
+
+    #[test]
+    fn initial_cells_keep_their_states() {
+        let mut world = World::new(6, 6, 0, Rule::default());
+        world.automaton = Automaton::Wireworld;
+        world.stamp(&[(0, 2), (1, 2), (2, 2)], (0, 0), State::CONDUCTOR);
+        world.set_cell_state(world.index(1, 2), State::HEAD);
+        world.set_cell_state(world.index(4, 4), State::IMMUTABLE);
+        let replay = Replay::new(&world).unwrap();
+
+        let text = replay.write();
+        assert!(
+            text.contains("\ncells 12:conductor 13:head 14:conductor 28:immutable\n"),
+            "{}",
+            text
+        );
+        let parsed = Replay::parse(&text).unwrap();
+        let replayed = parsed.initial_world();
+        for index in 0..world.cell_count() {
+            assert_eq!(replayed.cell_state(index), world.cell_state(index));
+        }
+    }
+
+    #[test]
+    fn initial_indexes_of_older_replays_are_read_as_alive_cells() {
+        let replay = Replay::new(&World::new(4, 4, 0, Rule::default())).unwrap();
+        let text = replay.write().replace("\ncells\n", "\ncells 1 5 9\n");
+
+        let world = Replay::parse(&text).unwrap().initial_world();
+        assert_eq!(world.live_cells(), vec![1, 5, 9]);
+        assert!(Replay::parse(&text.replace("cells 1 5 9", "cells 1 5 16")).is_err());
+    }
+
+    #[test]
+    fn restored_ages_of_older_replays_are_read_as_alive_cells() {
+        let replay = Replay::new(&World::new(4, 4, 0, Rule::default())).unwrap();
+        let text = replay
+            .write()
+            .replace("end 0", "0 restore 5 2:7 6:0\nend 0");
+
+        let parsed = Replay::parse(&text).unwrap();
+        let mut world = parsed.initial_world();
+        parsed.player().play(&mut world);
+        assert_eq!(world.live_cells(), vec![2, 6]);
+        assert_eq!(world.generation(), 5);
+    }
+}
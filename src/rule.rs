@@ -0,0 +1,583 @@
+//! Life-like rules, written in `B3/S23` notation: the numbers of alive
+//! neighbours for which a dead cell is born, then those for which an alive
+//! cell survives. Larger than Life rules, counting more than 8 neighbours,
+//! list their counts and ranges of counts instead, as in `B34-45/S34-58`.
+
+use crate::automata::State;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Set of numbers of neighbours, from 0 to `Counts::MAX`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Counts([u64; 8]);
+
+impl Counts {
+    /// Largest count of a set, above the 440 neighbours of a Moore
+    /// neighbourhood of radius 10 and the cell itself.
+    pub const MAX: usize = 511;
+
+    pub fn contains(&self, count: usize) -> bool {
+        count <= Self::MAX && self.0[count / 64] >> (count % 64) & 1 == 1
+    }
+
+    /// Add `count` to the set, returning whether it was not in it yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is above `Counts::MAX`.
+    pub fn insert(&mut self, count: usize) -> bool {
+        assert!(count <= Self::MAX, "count {} above {}", count, Self::MAX);
+        let added = !self.contains(count);
+        self.0[count / 64] |= 1 << (count % 64);
+        added
+    }
+
+    /// Counts of the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..=Self::MAX).filter(move |&count| self.contains(count))
+    }
+}
+
+impl std::iter::FromIterator<usize> for Counts {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut counts = Self::default();
+        for count in iter {
+            counts.insert(count);
+        }
+        counts
+    }
+}
+
+impl fmt::Display for Counts {
+    /// Digits when every count is at most 8, as in `23`, comma separated
+    /// counts and ranges of counts otherwise, as in `9-12,20`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.iter().all(|count| count <= 8) {
+            return self.iter().try_for_each(|count| write!(f, "{}", count));
+        }
+
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for count in self.iter() {
+            match ranges.last_mut() {
+                Some((_, last)) if *last + 1 == count => *last = count,
+                _ => ranges.push((count, count)),
+            }
+        }
+        for (i, &(first, last)) in ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            // A lone count is written as a range, not to be read as digits
+            if first == last && ranges.len() > 1 {
+                write!(f, "{}", first)?;
+            } else {
+                write!(f, "{}-{}", first, last)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rule {
+    /// Numbers of alive neighbours for which a dead cell is born
+    pub birth: Counts,
+    /// Numbers of alive neighbours for which an alive cell lives on
+    pub survive: Counts,
+    /// Count the cell itself among its alive neighbours (inner-totalistic
+    /// rule), so an alive cell with 8 alive neighbours counts 9 and dies
+    pub include_center: bool,
+}
+
+impl Default for Rule {
+    /// Conway's Game of Life, `B3/S23`.
+    fn default() -> Self {
+        Self {
+            birth: [3].iter().copied().collect(),
+            survive: [2, 3].iter().copied().collect(),
+            include_center: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleParseError {
+    pub rule: String,
+    pub message: String,
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid rule `{}`: {}", self.rule, self.message)
+    }
+}
+
+impl error::Error for RuleParseError {}
+
+/// Parse the neighbour counts following the `B` or `S` prefix of a part.
+fn parse_counts(part: &str, prefix: char) -> Result<Counts, String> {
+    let mut chars = part.chars();
+    if !chars
+        .next()
+        .is_some_and(|c| c.eq_ignore_ascii_case(&prefix))
+    {
+        return Err(format!("expected `{}` at the start of `{}`", prefix, part));
+    }
+
+    parse_count_list(chars.as_str())
+}
+
+/// Parse a list of neighbour counts, either digits such as `23` or, for
+/// counts above 8, comma separated counts and ranges such as `9-12,20`.
+fn parse_count_list(list: &str) -> Result<Counts, String> {
+    let mut counts = Counts::default();
+    let mut add = |count: usize| {
+        if counts.insert(count) {
+            Ok(())
+        } else {
+            Err(format!("duplicate count {} in `{}`", count, list))
+        }
+    };
+
+    if !list.contains(&[',', '-'][..]) {
+        for c in list.chars() {
+            match c.to_digit(10) {
+                Some(count) if count <= 8 => add(count as usize)?,
+                Some(count) => {
+                    return Err(format!(
+                    "digits list at most 8 neighbours, got {}, use ranges such as `9-9` past it",
+                    count
+                ))
+                }
+                None => return Err(format!("unexpected character `{}`", c)),
+            }
+        }
+        return Ok(counts);
+    }
+
+    let count = |text: &str| {
+        text.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&count| count <= Counts::MAX)
+            .ok_or_else(|| format!("invalid count `{}`, expected 0 to {}", text, Counts::MAX))
+    };
+    for item in list.split(',') {
+        let mut bounds = item.splitn(2, '-');
+        let first = count(bounds.next().unwrap_or(""))?;
+        let last = match bounds.next() {
+            Some(last) => count(last)?,
+            None => first,
+        };
+        if first > last {
+            return Err(format!("empty range `{}`", item));
+        }
+        for count in first..=last {
+            add(count)?;
+        }
+    }
+    Ok(counts)
+}
+
+impl Rule {
+    /// Parse a rule in `B3/S23` notation, case insensitive.
+    pub fn parse(rule: &str) -> Result<Self, RuleParseError> {
+        let error = |message: String| RuleParseError {
+            rule: rule.to_string(),
+            message,
+        };
+
+        let mut parts = rule.trim().splitn(2, '/');
+        let (birth, survive) = match (parts.next(), parts.next()) {
+            (Some(birth), Some(survive)) => (birth, survive),
+            _ => return Err(error("expected `B.../S...`, missing `/`".to_string())),
+        };
+
+        Ok(Self {
+            birth: parse_counts(birth, 'B').map_err(error)?,
+            survive: parse_counts(survive, 'S').map_err(error)?,
+            include_center: false,
+        })
+    }
+
+    /// Whether a cell in the `alive` state with `alive_neighbours` alive
+    /// neighbours (itself excluded) is alive at the next generation.
+    pub fn next_alive(&self, alive: bool, alive_neighbours: usize) -> bool {
+        let count = alive_neighbours + (self.include_center && alive) as usize;
+        let counts = if alive { &self.survive } else { &self.birth };
+        counts.contains(count)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B{}/S{}", self.birth, self.survive)
+    }
+}
+
+/// Rule of a block cellular automaton on the Margolus neighbourhood: the
+/// world is split in 2x2 blocks, shifted by one cell diagonally every other
+/// generation, and each block is replaced according to `table`.
+///
+/// Blocks are indexed by their alive cells, the top-left cell being bit 0,
+/// the top-right bit 1, the bottom-left bit 2 and the bottom-right bit 3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockRule {
+    pub table: [u8; 16],
+}
+
+/// Block turned half a turn: the top-left and bottom-right cells are swapped,
+/// as are the top-right and bottom-left ones.
+fn rotate_block(block: u8) -> u8 {
+    (block & 0b0001) << 3 | (block & 0b0010) << 1 | (block & 0b0100) >> 1 | (block & 0b1000) >> 3
+}
+
+impl BlockRule {
+    fn from_fn(next: impl Fn(u8) -> u8) -> Self {
+        let mut table = [0; 16];
+        for (block, next_block) in table.iter_mut().enumerate() {
+            *next_block = next(block as u8) & 0b1111;
+        }
+        Self { table }
+    }
+
+    /// Critters: blocks of two alive cells are kept, the others are
+    /// complemented, and turned half a turn if they had three alive cells.
+    pub fn critters() -> Self {
+        Self::from_fn(|block| match block.count_ones() {
+            2 => block,
+            3 => rotate_block(!block & 0b1111),
+            _ => !block,
+        })
+    }
+
+    /// Billiard ball machine: a lone cell moves to the opposite corner of its
+    /// block, two cells on a diagonal bounce off to the other diagonal, other
+    /// blocks are kept.
+    pub fn billiard_ball() -> Self {
+        Self::from_fn(|block| match block {
+            0b1001 => 0b0110,
+            0b0110 => 0b1001,
+            _ if block.count_ones() == 1 => rotate_block(block),
+            _ => block,
+        })
+    }
+
+    /// Whether every block has a single antecedent, in which case the
+    /// automaton can be run backward.
+    pub fn is_reversible(&self) -> bool {
+        let mut seen = [false; 16];
+        self.table
+            .iter()
+            .all(|&block| !std::mem::replace(&mut seen[block as usize], true))
+    }
+}
+
+/// Error met while reading a `TableRule`, with its line number (1-based).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TableParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for TableParseError {}
+
+/// Transition of a `TableRule`, applying to the cells in state `from` whose
+/// number of neighbours in each state of `tallies` is one of the counts
+/// listed for it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Transition {
+    pub from: u8,
+    pub tallies: Vec<(u8, Counts)>,
+    pub to: u8,
+}
+
+impl Transition {
+    fn applies(&self, state: u8, tally: &[usize]) -> bool {
+        self.from == state
+            && self.tallies.iter().all(|(neighbour_state, counts)| {
+                let count = tally.get(*neighbour_state as usize).copied().unwrap_or(0);
+                counts.contains(count)
+            })
+    }
+}
+
+/// Rule of an automaton with any number of states, given as a list of
+/// transitions keyed by the state of a cell and the tally of the states of
+/// its neighbours. The first transition applying to a cell gives its next
+/// state, a cell no transition applies to keeps its state.
+///
+/// The text format starts with a `states N` line, followed by one transition
+/// per line: the current state, then `state:counts` conditions on the number
+/// of neighbours in a state, then `->` and the next state. Lines starting with
+/// `;` are comments. Conway's Game of Life reads:
+///
+/// ```text
+/// states 2
+/// 0 1:3 -> 1
+/// 1 1:23 -> 1
+/// 1 -> 0
+/// ```
+///
+/// and Brian's Brain (dead, on, dying):
+///
+/// ```text
+/// states 3
+/// 0 1:2 -> 1
+/// 1 -> 2
+/// 2 -> 0
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TableRule {
+    pub states: u8,
+    pub transitions: Vec<Transition>,
+}
+
+impl TableRule {
+    pub fn parse(text: &str) -> Result<Self, TableParseError> {
+        let mut states = None;
+        let mut transitions = Vec::new();
+        let mut last_line = 0;
+
+        for (line, content) in text.lines().enumerate() {
+            let line = line + 1;
+            let content = content.trim();
+            last_line = line;
+            if content.is_empty() || content.starts_with(';') {
+                continue;
+            }
+
+            let error = |message: String| TableParseError { line, message };
+            let states = match states {
+                Some(states) => states,
+                None => {
+                    let count = content
+                        .strip_prefix("states")
+                        .and_then(|count| count.trim().parse::<u8>().ok())
+                        .filter(|&count| count >= 2)
+                        .ok_or_else(|| {
+                            error(format!(
+                                "expected `states N` with N >= 2, got `{}`",
+                                content
+                            ))
+                        })?;
+                    states = Some(count);
+                    continue;
+                }
+            };
+
+            let state = |value: &str| {
+                value
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|&state| state < states)
+                    .ok_or_else(|| error(format!("invalid state `{}`", value)))
+            };
+
+            let mut sides = content.splitn(2, "->").map(str::trim);
+            let (condition, to) = match (sides.next(), sides.next()) {
+                (Some(condition), Some(to)) => (condition, to),
+                _ => return Err(error(format!("missing `->` in `{}`", content))),
+            };
+            let mut words = condition.split_whitespace();
+            let from = state(words.next().unwrap_or(""))?;
+            let tallies = words
+                .map(|tally| {
+                    let mut parts = tally.splitn(2, ':');
+                    match (parts.next(), parts.next()) {
+                        (Some(neighbour_state), Some(counts)) => Ok((
+                            state(neighbour_state)?,
+                            parse_count_list(counts).map_err(&error)?,
+                        )),
+                        _ => Err(error(format!("expected `state:counts`, got `{}`", tally))),
+                    }
+                })
+                .collect::<Result<_, _>>()?;
+
+            transitions.push(Transition {
+                from,
+                tallies,
+                to: state(to)?,
+            });
+        }
+
+        match states {
+            Some(states) => Ok(Self {
+                states,
+                transitions,
+            }),
+            None => Err(TableParseError {
+                line: last_line,
+                message: "missing `states N` line".to_string(),
+            }),
+        }
+    }
+
+    /// Brian's Brain, on the states of a `World` (see `State::TABLE_STATES`):
+    /// off (dead) cells turn on (alive) next to exactly two on cells, on
+    /// cells start dying and the others turn off.
+    pub fn brians_brain() -> Self {
+        Self::parse("states 6\n0 1:2 -> 1\n1 -> 2\n2 -> 0\n3 -> 0\n4 -> 0\n5 -> 0")
+            .expect("the Brian's Brain table is valid")
+    }
+
+    /// Wireworld, on the states of a `World` (see `State::TABLE_STATES`):
+    /// heads become tails, tails become conductors, and conductors (or alive
+    /// cells) become heads next to one or two heads. Dying cells turn dead.
+    pub fn wireworld() -> Self {
+        Self::parse("states 6\n1 4:12 -> 4\n1 -> 3\n2 -> 0\n3 4:12 -> 4\n4 -> 5\n5 -> 3")
+            .expect("the Wireworld table is valid")
+    }
+
+    /// Next state of a cell in `state`, `tally[s]` being the number of its
+    /// neighbours in state `s`.
+    pub fn next_state(&self, state: u8, tally: &[usize]) -> u8 {
+        self.transitions
+            .iter()
+            .find(|transition| transition.applies(state, tally))
+            .map_or(state, |transition| transition.to)
+    }
+}
+
+/// Rule of a custom automaton, run with `Automaton::Custom`: the next state
+/// of a cell from its current state and the states of its neighbours, in the
+/// order of the neighbourhood of the world. Rules are shared between the
+/// threads of the parallel update, so `next_state` must not depend on the
+/// order cells are updated in.
+pub trait CellRule: fmt::Debug + Send + Sync {
+    fn next_state(&self, current: State, neighbour_states: &[State]) -> State;
+}
+
+/// Conway's Game of Life as a `CellRule`, the same as the default `Rule`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConwayRule;
+
+impl CellRule for ConwayRule {
+    fn next_state(&self, current: State, neighbour_states: &[State]) -> State {
+        let alive_neighbours = neighbour_states
+            .iter()
+            .filter(|&&state| state == State::ALIVE)
+            .count();
+        match (current, alive_neighbours) {
+            (State::ALIVE, 2) | (_, 3) => State::ALIVE,
+            _ => State::DEAD,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays_life_like_rules() {
+        let highlife = Rule::parse("b36/s23").unwrap();
+
+        assert!(highlife.next_alive(false, 3) && highlife.next_alive(false, 6));
+        assert!(highlife.next_alive(true, 2) && !highlife.next_alive(true, 6));
+        assert_eq!(highlife.to_string(), "B36/S23");
+        assert_eq!("B3/S23".parse::<Rule>(), Ok(Rule::default()));
+        assert_eq!(Rule::parse("B/S").unwrap().to_string(), "B/S");
+    }
+
+    #[test]
+    fn formatted_rules_parse_back_the_same() {
+        for text in &[
+            "B3/S23",
+            "B36/S23",
+            "B2/S",
+            "B3678/S34678",
+            "B012345678/S012345678",
+            "B/S",
+            "B34-45/S34-58",
+            "B9-9/S2,5,9-12",
+        ] {
+            let rule = Rule::parse(text).unwrap();
+            assert_eq!(rule.to_string(), *text);
+            assert_eq!(Rule::parse(&rule.to_string()), Ok(rule));
+        }
+        // Digits are written in ascending order
+        assert_eq!(Rule::parse("b63/s32").unwrap().to_string(), "B36/S23");
+    }
+
+    #[test]
+    fn malformed_rules_are_refused() {
+        for rule in &[
+            "B3S23",
+            "S23/B3",
+            "B39/S23",
+            "B33/S23",
+            "B3/S2x",
+            "B9-8/S",
+            "B3-4,4/S",
+            "B512-512/S",
+        ] {
+            assert!(Rule::parse(rule).is_err(), "{} parsed", rule);
+        }
+    }
+
+    #[test]
+    fn counts_past_eight_are_listed_as_ranges() {
+        let bosco = Rule::parse("B34-45/S34-58").unwrap();
+
+        assert!(bosco.next_alive(false, 34) && bosco.next_alive(false, 45));
+        assert!(!bosco.next_alive(false, 33) && !bosco.next_alive(false, 46));
+        assert!(bosco.next_alive(true, 58) && !bosco.next_alive(true, 59));
+        assert_eq!(Rule::parse("B3,12/S2-3").unwrap().to_string(), "B3,12/S23");
+        // Ranges of counts up to 8 are written as digits
+        assert_eq!(Rule::parse("B3-3/S2-3"), Ok(Rule::default()));
+        assert!(!Rule::default().next_alive(false, Counts::MAX + 1));
+    }
+
+    #[test]
+    fn center_counts_toward_survival_only_for_alive_cells() {
+        let outer = Rule::default();
+        let inner = Rule {
+            include_center: true,
+            ..outer
+        };
+
+        assert!(!outer.next_alive(true, 1));
+        assert!(inner.next_alive(true, 1));
+        assert!(!inner.next_alive(true, 3));
+        // Dead cells have no center to count
+        assert!(inner.next_alive(false, 3));
+        assert!(!inner.next_alive(false, 2));
+    }
+
+    #[test]
+    fn brians_brain_table() {
+        let brain = TableRule::parse("states 3\n0 1:2 -> 1\n1 -> 2\n2 -> 0").unwrap();
+
+        assert_eq!(brain.next_state(0, &[6, 2, 0]), 1);
+        assert_eq!(brain.next_state(0, &[5, 3, 0]), 0);
+        assert_eq!(brain.next_state(1, &[8, 0, 0]), 2);
+        assert_eq!(brain.next_state(2, &[0, 2, 6]), 0);
+    }
+
+    #[test]
+    fn table_without_states_line_is_refused() {
+        assert_eq!(
+            TableRule::parse("0 1:3 -> 1"),
+            Err(TableParseError {
+                line: 1,
+                message: "expected `states N` with N >= 2, got `0 1:3 -> 1`".to_string()
+            })
+        );
+    }
+}
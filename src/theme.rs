@@ -0,0 +1,283 @@
+use log::warn;
+use std::error;
+use std::fmt;
+
+/// Colors used to draw the cells, as RGBA.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub alive: [u8; 4],
+    pub dead: [u8; 4],
+    /// Color reached by cells alive for `MATURE_AGE` generations, when
+    /// coloring cells by age
+    pub mature: [u8; 4],
+    /// Color of the cells outside of the active mask of the world
+    pub background: [u8; 4],
+    /// Colors of the Wireworld states
+    pub conductor: [u8; 4],
+    pub head: [u8; 4],
+    pub tail: [u8; 4],
+    /// Color of the dying cells of Brian's Brain
+    pub dying: [u8; 4],
+    /// Color of the immutable cells, walls that never change
+    pub immutable: [u8; 4],
+}
+
+impl Default for Theme {
+    /// Dark cells on a light background.
+    fn default() -> Self {
+        Self {
+            alive: [0x1E, 0x1E, 0x1E, 0xFF],
+            dead: [0xF8, 0xF8, 0xF8, 0xFF],
+            mature: [0xF0, 0x8C, 0x28, 0xFF],
+            background: [0x60, 0x60, 0x60, 0xFF],
+            conductor: [0xF0, 0xB4, 0x28, 0xFF],
+            head: [0x28, 0x64, 0xF0, 0xFF],
+            tail: [0xF0, 0x46, 0x28, 0xFF],
+            dying: [0x3C, 0x78, 0xC8, 0xFF],
+            immutable: [0x28, 0x32, 0x50, 0xFF],
+        }
+    }
+}
+
+/// Age from which alive cells are drawn with the `mature` color.
+pub const MATURE_AGE: u32 = 32;
+
+/// How the world is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DrawOptions {
+    pub theme: Theme,
+    /// Shade alive cells from `alive` to `mature` as they age
+    pub age_colors: bool,
+    /// Index of the cell under the keyboard cursor, if shown
+    pub cursor: Option<usize>,
+    /// Tint the cells along the edges of the visible area, where the world
+    /// wraps around
+    pub seams: bool,
+    /// Draw dead cells fully transparent (`[0, 0, 0, 0]`) instead of with
+    /// the `dead` color, to lay the world over a background
+    pub transparent_dead: bool,
+    /// Generations over which the cells that die fade from a faint `alive`
+    /// color back to `dead`, leaving a trail behind moving patterns, `0`
+    /// drawing no trail. Capped at `MAX_TRAIL`.
+    pub trail: u8,
+}
+
+/// Tint added to the color of frozen cells.
+const FROZEN_TINT: [u8; 4] = [0x50, 0x78, 0xF8, 0xFF];
+
+/// Tint mixed into the color of the cells along the seams of the world.
+const SEAM_TINT: [u8; 4] = [0x28, 0xB4, 0x78, 0xFF];
+
+/// Tint mixed into the color of the cell under the keyboard cursor.
+const CURSOR_TINT: [u8; 4] = [0xE8, 0x30, 0x30, 0xFF];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaletteError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for PaletteError {}
+
+/// Parse a `RRGGBB` or `RRGGBBAA` hexadecimal color, optionally prefixed by `#`.
+fn parse_color(value: &str) -> Option<[u8; 4]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut rgba = [0xFF; 4];
+    for (i, channel) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(rgba)
+}
+
+impl Theme {
+    /// Light cells on a dark background, the other states keeping their
+    /// default color.
+    pub fn dark() -> Self {
+        Self {
+            alive: [0xF8, 0xF8, 0xF8, 0xFF],
+            dead: [0x1E, 0x1E, 0x1E, 0xFF],
+            background: [0x3C, 0x3C, 0x3C, 0xFF],
+            immutable: [0x78, 0x82, 0xA0, 0xFF],
+            ..Self::default()
+        }
+    }
+
+    /// Read a palette made of `state = #RRGGBB[AA]` lines, lines starting
+    /// with `;` being comments. States missing from the palette keep their
+    /// default color.
+    pub fn from_palette(text: &str) -> Result<Self, PaletteError> {
+        let mut theme = Self::default();
+
+        for (line, content) in text.lines().enumerate() {
+            let line = line + 1;
+            let content = content.trim();
+            if content.is_empty() || content.starts_with(';') {
+                continue;
+            }
+
+            let error = |message: String| PaletteError { line, message };
+            let mut parts = content.splitn(2, '=').map(str::trim);
+            let (name, value) = match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => (name, value),
+                _ => {
+                    return Err(error(format!(
+                        "expected `state = #RRGGBB`, got `{}`",
+                        content
+                    )))
+                }
+            };
+            let color =
+                parse_color(value).ok_or_else(|| error(format!("invalid color `{}`", value)))?;
+
+            match name {
+                "alive" => theme.alive = color,
+                "dead" => theme.dead = color,
+                "mature" => theme.mature = color,
+                "background" => theme.background = color,
+                "conductor" => theme.conductor = color,
+                "head" => theme.head = color,
+                "tail" => theme.tail = color,
+                "dying" => theme.dying = color,
+                "immutable" => theme.immutable = color,
+                _ => warn!("palette line {}: ignoring unknown state `{}`", line, name),
+            }
+        }
+
+        Ok(theme)
+    }
+
+    /// Color of a cell alive for `age` generations, when coloring by age.
+    pub fn aged(&self, age: u32) -> [u8; 4] {
+        let age = age.min(MATURE_AGE);
+        let mut rgba = self.alive;
+        for (channel, mature) in rgba.iter_mut().zip(self.mature.iter()) {
+            let (from, to) = (*channel as u32, *mature as u32);
+            *channel = ((from * (MATURE_AGE - age) + to * age) / MATURE_AGE) as u8;
+        }
+        rgba
+    }
+
+    /// Color of a dead cell `remaining` generations before the end of a
+    /// trail `length` generations long: up to half of the way from `dead` to
+    /// `alive` for a cell that just died.
+    pub fn trail(&self, remaining: u8, length: u8) -> [u8; 4] {
+        let (remaining, length) = (remaining.min(length) as i32, length.max(1) as i32);
+        let mut rgba = self.dead;
+        for (channel, alive) in rgba.iter_mut().zip(self.alive.iter()) {
+            let (from, to) = (*channel as i32, *alive as i32);
+            *channel = (from + (to - from) * remaining / (2 * length)) as u8;
+        }
+        rgba
+    }
+
+    /// Color of a frozen cell otherwise drawn with `color`.
+    pub fn frozen(&self, color: [u8; 4]) -> [u8; 4] {
+        let mut tinted = color;
+        for (channel, tint) in tinted.iter_mut().zip(FROZEN_TINT.iter()).take(3) {
+            *channel = ((*channel as u16 * 3 + *tint as u16) / 4) as u8;
+        }
+        tinted
+    }
+
+    /// Color of a cell along the seams of the world otherwise drawn with `color`.
+    pub fn seam(&self, color: [u8; 4]) -> [u8; 4] {
+        let mut tinted = color;
+        for (channel, tint) in tinted.iter_mut().zip(SEAM_TINT.iter()).take(3) {
+            *channel = ((*channel as u16 * 3 + *tint as u16) / 4) as u8;
+        }
+        tinted
+    }
+
+    /// Color of the cell under the keyboard cursor otherwise drawn with `color`.
+    pub fn highlighted(&self, color: [u8; 4]) -> [u8; 4] {
+        let mut tinted = color;
+        for (channel, tint) in tinted.iter_mut().zip(CURSOR_TINT.iter()) {
+            *channel = ((*channel as u16 + *tint as u16) / 2) as u8;
+        }
+        tinted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::{State, World};
+    use crate::rule::Rule;
+
+    #[test]
+    fn default_colors_are_opaque() {
+        let theme = Theme::default();
+        assert_eq!(theme.dead, [0xF8, 0xF8, 0xF8, 0xFF]);
+        for color in [theme.alive, theme.dead, theme.immutable, theme.background].iter() {
+            assert_eq!(color[3], 0xFF);
+        }
+    }
+
+    #[test]
+    fn ages_ramp_from_alive_to_mature() {
+        let theme = Theme::default();
+        assert_eq!(theme.aged(0), theme.alive);
+        assert_eq!(theme.aged(MATURE_AGE), theme.mature);
+        assert_eq!(theme.aged(MATURE_AGE * 10), theme.mature);
+
+        let mut world = World::new(6, 6, 0, Rule::default());
+        world.stamp(&[(0, 0), (1, 0), (0, 1), (1, 1)], (2, 2), State::ALIVE);
+        for generation in 1..=MATURE_AGE {
+            world.step();
+            assert_eq!(world.age(world.index(2, 2)), Some(generation));
+        }
+    }
+
+    #[test]
+    fn palettes_override_the_default_colors() {
+        let palette = "; high contrast\nalive = #FF0000\n\ndead=000000\nimmutable = #0000FF80\n";
+        let theme = Theme::from_palette(palette).unwrap();
+
+        assert_eq!(theme.alive, [0xFF, 0, 0, 0xFF]);
+        assert_eq!(theme.dead, [0, 0, 0, 0xFF]);
+        assert_eq!(theme.immutable, [0, 0, 0xFF, 0x80]);
+        assert_eq!(theme.head, Theme::default().head);
+    }
+
+    #[test]
+    fn malformed_palettes_are_located() {
+        let error = Theme::from_palette("alive = #FF0000\ndead = #GG0000\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(
+            Theme::from_palette("\n\nalive #FF0000").unwrap_err().line,
+            3
+        );
+    }
+
+    #[test]
+    fn worlds_are_drawn_with_the_theme() {
+        let mut world = World::new(3, 1, 0, Rule::default());
+        world.set_cell_state(world.index(0, 0), State::ALIVE);
+        world.set_cell_state(world.index(2, 0), State::IMMUTABLE);
+        let options = DrawOptions {
+            theme: Theme::dark(),
+            ..DrawOptions::default()
+        };
+        let mut frame = vec![0; 3 * 4];
+        world.draw(&mut frame, &options);
+
+        let dark = Theme::dark();
+        assert_eq!(&frame[..4], dark.alive);
+        assert_eq!(&frame[4..8], dark.dead);
+        assert_eq!(&frame[8..], dark.immutable);
+        assert_eq!(
+            (dark.alive, dark.dead),
+            (Theme::default().dead, Theme::default().alive)
+        );
+    }
+}